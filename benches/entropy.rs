@@ -0,0 +1,76 @@
+// Entropy filter on a large log-like input: `cargo bench --bench entropy`
+//
+// Each line is tokenized once and every candidate token scored; most lines
+// carry a token long enough to be scored but not redacted, one in ten a
+// random one that is.
+
+use kahl::{EntropyConfig, FilterConfig, RedactorBuilder};
+use std::hint::black_box;
+use std::time::Instant;
+
+const LINES: usize = 200_000;
+
+fn main() {
+    // Deterministic pseudo-random tokens (xorshift), so runs are comparable
+    let mut state: u64 = 0x9e37_79b9_7f4a_7c15;
+    let mut random_token = |len: usize| -> String {
+        const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                ALPHABET[(state % ALPHABET.len() as u64) as usize] as char
+            })
+            .collect()
+    };
+    let lines: Vec<String> = (0..LINES)
+        .map(|i| match i % 10 {
+            0 => format!("upload done, data {} stored", random_token(40)),
+            _ => format!(
+                "2024-05-01T12:{:02}:{:02}Z GET /api/v2/items/{} user=svc-{} trace=a1b2c3d4-{} size={}kb",
+                i % 60,
+                i % 59,
+                i,
+                i % 13,
+                i % 1000,
+                i % 977
+            ),
+        })
+        .collect();
+    let bytes: usize = lines.iter().map(|l| l.len() + 1).sum();
+
+    let redactor = RedactorBuilder::new()
+        .filters(FilterConfig {
+            values: false,
+            patterns: false,
+            entropy: true,
+        })
+        .entropy_config(EntropyConfig::default())
+        .build();
+
+    let start = Instant::now();
+    let mut redacted = 0;
+    for line in &lines {
+        if black_box(redactor.redact_line(line)).contains("[REDACTED:HIGH_ENTROPY:") {
+            redacted += 1;
+        }
+    }
+    let elapsed = start.elapsed();
+    // Only the random tokens are redacted (a few score under the threshold)
+    assert!(
+        redacted <= LINES / 10 && redacted > LINES / 20,
+        "{}",
+        redacted
+    );
+
+    println!(
+        "entropy: {} lines ({:.1} MB, {} redacted) in {:.1}ms ({:.0} lines/s, {:.1} MB/s)",
+        LINES,
+        bytes as f64 / 1e6,
+        redacted,
+        elapsed.as_secs_f64() * 1e3,
+        LINES as f64 / elapsed.as_secs_f64(),
+        bytes as f64 / 1e6 / elapsed.as_secs_f64()
+    );
+}
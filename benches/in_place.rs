@@ -0,0 +1,105 @@
+// In-place vs returning line redaction: `cargo bench --bench in_place`
+//
+// Counts heap allocations through a wrapping global allocator, separately
+// for clean and redacted lines. The in-place variant writes each result back
+// into one reused line buffer, saving the per-line result allocation of
+// `redact_line`; what the filters allocate internally is the same for both.
+
+use kahl::RedactorBuilder;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+const LINES: usize = 100_000;
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+fn main() {
+    let clean: Vec<String> = (0..LINES)
+        .map(|i| {
+            format!(
+                "request {} served in {}ms from cache node-{}",
+                i,
+                i % 97,
+                i % 13
+            )
+        })
+        .collect();
+    let redacted: Vec<String> = (0..LINES)
+        .map(|i| format!("login ok password=hunter{:06} at step {}", i, i))
+        .collect();
+    let redactor = RedactorBuilder::new().build();
+
+    // Redacted lines are what the in-place claim is about: their result used
+    // to be a new String; clean lines never change their buffer
+    for (kind, lines) in [("clean", &clean), ("redacted", &redacted)] {
+        let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+        let start = Instant::now();
+        let mut returning = Vec::with_capacity(LINES);
+        for line in lines {
+            returning.push(black_box(redactor.redact_line(line)));
+        }
+        report(
+            "redact_line",
+            kind,
+            ALLOCATIONS.load(Ordering::Relaxed) - allocations,
+            start,
+        );
+
+        // Room for the longest result up front, so the buffer never has to grow
+        let mut buf = String::with_capacity(256);
+        let ptr = buf.as_ptr();
+        let mut kept = 0;
+        let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+        let start = Instant::now();
+        for (line, expected) in lines.iter().zip(&returning) {
+            buf.clear();
+            buf.push_str(line);
+            redactor.redact_line_in_place(&mut buf);
+            assert_eq!(black_box(&buf), expected);
+            kept += usize::from(buf.as_ptr() == ptr);
+        }
+        report(
+            "redact_line_in_place",
+            kind,
+            ALLOCATIONS.load(Ordering::Relaxed) - allocations,
+            start,
+        );
+        println!("  caller's buffer kept for {} of {} lines", kept, LINES);
+    }
+}
+
+fn report(name: &str, kind: &str, allocations: usize, start: Instant) {
+    let elapsed = start.elapsed();
+    println!(
+        "{:<20} {:<8} {} lines in {:.1}ms, {} allocations ({:.2} per line)",
+        name,
+        kind,
+        LINES,
+        elapsed.as_secs_f64() * 1e3,
+        allocations,
+        allocations as f64 / LINES as f64
+    );
+}
@@ -0,0 +1,71 @@
+// Env value redaction with a large secrets set: `cargo bench --bench secrets`
+//
+// Secret values are compiled once when the redactor is built, so per-line
+// cost should stay flat as lines grow, in both matching modes.
+
+use kahl::{FilterConfig, RedactorBuilder};
+use std::collections::HashMap;
+use std::hint::black_box;
+use std::time::Instant;
+
+const SECRETS: usize = 2_000;
+const LINES: usize = 100_000;
+
+fn main() {
+    let secrets: HashMap<String, String> = (0..SECRETS)
+        .map(|i| (format!("SECRET_{}", i), format!("s3cr3t-value-{:06}-x", i)))
+        .collect();
+    let lines: Vec<String> = (0..LINES)
+        .map(|i| match i % 10 {
+            0 => format!(
+                "login ok with s3cr3t-value-{:06}-x at step {}",
+                i % SECRETS,
+                i
+            ),
+            _ => format!(
+                "request {} served in {}ms from cache node-{}",
+                i,
+                i % 97,
+                i % 13
+            ),
+        })
+        .collect();
+
+    for whole_word in [false, true] {
+        let start = Instant::now();
+        let redactor = RedactorBuilder::new()
+            .filters(FilterConfig {
+                values: true,
+                patterns: false,
+                entropy: false,
+            })
+            .secrets(secrets.clone())
+            .whole_word_secrets(whole_word)
+            .build();
+        let built = start.elapsed();
+
+        let start = Instant::now();
+        let mut redacted = 0;
+        for line in &lines {
+            if black_box(redactor.redact_line(line)).contains("[REDACTED:") {
+                redacted += 1;
+            }
+        }
+        let elapsed = start.elapsed();
+        assert_eq!(redacted, LINES / 10);
+
+        println!(
+            "{:<10} {} secrets: build {:.1}ms, {} lines in {:.1}ms ({:.0} lines/s)",
+            if whole_word {
+                "whole-word"
+            } else {
+                "substring"
+            },
+            SECRETS,
+            built.as_secs_f64() * 1e3,
+            LINES,
+            elapsed.as_secs_f64() * 1e3,
+            LINES as f64 / elapsed.as_secs_f64()
+        );
+    }
+}
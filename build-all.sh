@@ -15,7 +15,12 @@
 set -euo pipefail
 cd "$(dirname "$0")"
 
-VERSION=$(cat VERSION)
+# VERSION file is optional: fall back to git describe (same rules as build.rs)
+VERSION=$(tr -d '[:space:]' < VERSION 2>/dev/null || true)
+if [[ -z "$VERSION" ]]; then
+  VERSION=$(git describe --tags --match 'v[0-9]*' --exclude '*-*' 2>/dev/null | sed 's/^v//' || true)
+fi
+VERSION=${VERSION:-0.0.0}
 
 # Detect current platform
 case "$(uname -s)-$(uname -m)" in
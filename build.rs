@@ -8,6 +8,7 @@ fn main() {
     // 4. Cargo.toml version (final fallback)
     let version = std::env::var("RELEASE_VERSION")
         .ok()
+        .map(|s| s.trim().to_string())
         .filter(|s| !s.is_empty())
         .or_else(|| {
             // Try git describe with only proper semver tags (exclude -suffix tags)
@@ -80,20 +80,25 @@ EOF
 
     # Context patterns
     # Rust regex does NOT support lookbehind, so we use capture groups:
-    # (prefix)(value) with the secret in group 2
+    # (prefix)(value) with the secret in group 2, plus an optional (suffix)
+    # group that must follow the secret but is kept (lookahead stand-in)
     echo "/// Context patterns: (regex, label, capture_group)"
     echo "/// Rust regex lacks lookbehind - use capture groups instead"
     echo "/// The secret value is in the specified capture group"
     echo "pub const CONTEXT_PATTERNS: &[(&str, &str, usize)] = &["
 
     # Build context patterns with capture groups
-    local context_count prefix value combined
+    local context_count prefix value suffix combined
     context_count=$(yq '.context_patterns | length' "$PATTERNS_DIR/patterns.yaml")
     for ((i=0; i<context_count; i++)); do
         prefix=$(yq -r ".context_patterns[$i].prefix" "$PATTERNS_DIR/patterns.yaml")
         value=$(yq -r ".context_patterns[$i].value" "$PATTERNS_DIR/patterns.yaml")
+        suffix=$(yq -r ".context_patterns[$i].suffix // \"\"" "$PATTERNS_DIR/patterns.yaml")
         label=$(yq -r ".context_patterns[$i].label" "$PATTERNS_DIR/patterns.yaml")
         combined="($prefix)($value)"
+        if [[ -n "$suffix" ]]; then
+            combined="$combined($suffix)"
+        fi
         echo "    ($(rust_raw_string "$combined"), \"$label\", 2),"
     done
 
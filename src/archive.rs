@@ -0,0 +1,67 @@
+// Tar archives for --tar
+//
+// The archive is rewritten member by member: text members (regular files
+// without a NUL byte) are redacted and get their size updated; directories,
+// links and binary members are copied unchanged. gzip input is detected by
+// its magic bytes, and the output is then compressed as well.
+
+use flate2::Compression;
+use flate2::bufread::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use std::io::{self, BufRead, Read, Write};
+use tar::{Archive, Builder};
+
+/// First bytes of a gzip stream
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Copy a tar (or tar.gz) stream from `input` to `output`, passing the
+/// content of each text member through `redact`
+pub fn filter_tar(
+    mut input: impl BufRead,
+    output: impl Write,
+    redact: impl FnMut(&str) -> String,
+) -> io::Result<()> {
+    if input.fill_buf()?.starts_with(&GZIP_MAGIC) {
+        let mut encoder = GzEncoder::new(output, Compression::default());
+        rewrite(MultiGzDecoder::new(input), &mut encoder, redact)?;
+        encoder.finish()?.flush()
+    } else {
+        rewrite(input, output, redact)
+    }
+}
+
+/// Rewrite an uncompressed tar stream
+fn rewrite(
+    input: impl Read,
+    output: impl Write,
+    mut redact: impl FnMut(&str) -> String,
+) -> io::Result<()> {
+    let mut archive = Archive::new(input);
+    let mut builder = Builder::new(output);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let mut header = entry.header().clone();
+        // Long names come from GNU/PAX extension entries, not the header
+        let path = entry.path()?.into_owned();
+        if let Some(target) = entry.link_name()? {
+            let target = target.into_owned();
+            builder.append_link(&mut header, &path, &target)?;
+            continue;
+        }
+
+        let mut data = Vec::new();
+        entry.read_to_end(&mut data)?;
+        if header.entry_type().is_file() && !data.contains(&0) {
+            // Unchanged members keep their bytes, so invalid UTF-8 survives
+            // unless something had to be redacted
+            let text = String::from_utf8_lossy(&data);
+            let redacted = redact(&text);
+            if redacted != text {
+                data = redacted.into_bytes();
+                header.set_size(data.len() as u64);
+            }
+        }
+        builder.append_data(&mut header, &path, data.as_slice())?;
+    }
+    builder.into_inner()?.flush()
+}
@@ -0,0 +1,109 @@
+// English bigram scoring for the `bigram` entropy model
+//
+// Shannon entropy only counts characters, so a long slug of English words
+// scores like a random token. Here each pair of adjacent letters is weighed
+// by how common it is in English text: word-like strings are made of common
+// pairs, random tokens mostly of pairs English rarely or never uses.
+
+/// Share of all letter pairs in English text, in percent (the most common
+/// pairs; any other pair counts as `RARE_PERCENT`)
+#[rustfmt::skip]
+const ENGLISH_BIGRAMS: &[(&str, f64)] = &[
+    ("th", 3.56), ("he", 3.07), ("in", 2.43), ("er", 2.05), ("an", 1.99),
+    ("re", 1.85), ("on", 1.76), ("at", 1.49), ("en", 1.45), ("nd", 1.35),
+    ("ti", 1.34), ("es", 1.34), ("or", 1.28), ("te", 1.20), ("of", 1.17),
+    ("ed", 1.17), ("is", 1.13), ("it", 1.12), ("al", 1.09), ("ar", 1.07),
+    ("st", 1.05), ("to", 1.04), ("nt", 1.04), ("ng", 0.95), ("se", 0.93),
+    ("ha", 0.93), ("as", 0.87), ("ou", 0.87), ("io", 0.83), ("le", 0.83),
+    ("ve", 0.83), ("co", 0.79), ("me", 0.79), ("de", 0.76), ("hi", 0.76),
+    ("ri", 0.73), ("ro", 0.73), ("ic", 0.70), ("ne", 0.69), ("ea", 0.69),
+    ("ra", 0.69), ("ce", 0.65), ("li", 0.62), ("ch", 0.60), ("ll", 0.58),
+    ("be", 0.58), ("ma", 0.57), ("si", 0.55), ("om", 0.55), ("ur", 0.54),
+    ("ca", 0.54), ("el", 0.53), ("ta", 0.53), ("la", 0.53), ("ns", 0.51),
+    ("di", 0.49), ("fo", 0.49), ("ho", 0.49), ("pe", 0.49), ("ec", 0.48),
+    ("pr", 0.47), ("no", 0.47), ("ct", 0.46), ("us", 0.45), ("ac", 0.45),
+    ("ot", 0.45), ("il", 0.43), ("tr", 0.43), ("ly", 0.43), ("nc", 0.42),
+    ("et", 0.42), ("ut", 0.42), ("ss", 0.41), ("so", 0.40), ("rs", 0.40),
+    ("un", 0.39), ("lo", 0.39), ("wa", 0.39), ("ge", 0.38), ("ie", 0.38),
+    ("wh", 0.38), ("ee", 0.38), ("wi", 0.38), ("em", 0.38), ("ad", 0.37),
+    ("ol", 0.37), ("rt", 0.37), ("po", 0.37), ("we", 0.36), ("na", 0.36),
+    ("ul", 0.35), ("ni", 0.35), ("ts", 0.35), ("mo", 0.34), ("ow", 0.34),
+    ("pa", 0.34), ("im", 0.34), ("mi", 0.34), ("ai", 0.33), ("sh", 0.32),
+    ("ir", 0.32), ("su", 0.32), ("id", 0.30), ("os", 0.30), ("iv", 0.29),
+    ("ia", 0.29), ("am", 0.29), ("fi", 0.29), ("ci", 0.29), ("vi", 0.27),
+    ("pl", 0.26), ("ig", 0.26), ("tu", 0.26), ("ev", 0.25), ("ld", 0.25),
+    ("ry", 0.25), ("mp", 0.25), ("fe", 0.25), ("bl", 0.24), ("ab", 0.23),
+    ("gh", 0.23), ("ty", 0.22), ("op", 0.22), ("wo", 0.22), ("sa", 0.22),
+    ("ay", 0.21), ("ex", 0.21), ("ke", 0.21), ("fr", 0.21), ("oo", 0.21),
+    ("av", 0.20), ("ag", 0.20), ("if", 0.20), ("ap", 0.20), ("gr", 0.20),
+    ("od", 0.20), ("bo", 0.20), ("sp", 0.20), ("rd", 0.19), ("do", 0.19),
+    ("uc", 0.19), ("bu", 0.19), ("ei", 0.18), ("ov", 0.18), ("by", 0.18),
+    ("rm", 0.18), ("ep", 0.17), ("tt", 0.17), ("oc", 0.17), ("fa", 0.17),
+    ("ef", 0.16), ("cu", 0.16), ("rn", 0.16), ("sc", 0.16), ("gi", 0.16),
+    ("da", 0.16), ("yo", 0.16), ("cr", 0.16), ("cl", 0.16), ("du", 0.16),
+    ("ga", 0.15), ("qu", 0.15), ("ue", 0.15), ("ff", 0.15), ("ba", 0.15),
+    ("ey", 0.15), ("ls", 0.15), ("va", 0.14), ("um", 0.14), ("pp", 0.14),
+    ("ua", 0.14), ("up", 0.14), ("lu", 0.14), ("go", 0.14), ("ht", 0.14),
+    ("ru", 0.13), ("ug", 0.13), ("ds", 0.13), ("lt", 0.13), ("pi", 0.13),
+    ("rc", 0.13), ("rr", 0.13), ("eg", 0.13), ("au", 0.13), ("ck", 0.13),
+    ("ew", 0.13), ("mu", 0.13), ("br", 0.13), ("bi", 0.12), ("pt", 0.12),
+    ("ak", 0.12), ("pu", 0.12), ("ui", 0.12), ("rg", 0.12), ("ib", 0.12),
+    ("tl", 0.12), ("ny", 0.12), ("ki", 0.12), ("rk", 0.12), ("ys", 0.11),
+    ("ob", 0.11), ("mm", 0.11), ("fu", 0.11), ("ph", 0.11), ("og", 0.11),
+    ("ms", 0.11), ("ye", 0.11), ("ud", 0.11), ("mb", 0.11), ("ip", 0.11),
+    ("ub", 0.11), ("oi", 0.11), ("rl", 0.11), ("gu", 0.11), ("dr", 0.11),
+    ("hr", 0.11), ("cc", 0.11), ("tw", 0.10), ("ft", 0.10), ("wn", 0.10),
+    ("nu", 0.10), ("af", 0.10), ("hu", 0.10), ("nn", 0.10), ("eo", 0.10),
+    ("vo", 0.10), ("rv", 0.10), ("nf", 0.10), ("xp", 0.09), ("gn", 0.09),
+    ("sm", 0.09), ("fl", 0.09), ("iz", 0.09), ("ok", 0.09), ("nl", 0.09),
+    ("my", 0.09), ("gl", 0.09), ("aw", 0.09), ("ju", 0.09), ("oa", 0.09),
+    ("eq", 0.09), ("sy", 0.08), ("sl", 0.08), ("ps", 0.08), ("jo", 0.08),
+    ("lf", 0.08), ("nv", 0.08), ("je", 0.08), ("nk", 0.08), ("kn", 0.08),
+    ("gs", 0.08), ("dy", 0.08), ("hy", 0.08), ("ze", 0.08),
+];
+
+/// Share assumed for a pair not in the table, in percent
+const RARE_PERCENT: f64 = 0.01;
+
+/// Share of a pair if letters were uniformly random, in percent
+const UNIFORM_PERCENT: f64 = 100.0 / (26.0 * 26.0);
+
+/// Fewer scored pairs than this say too little either way
+const MIN_PAIRS: usize = 6;
+
+/// Bits per pair above which a token reads as English words
+pub const WORD_LIKE_SCORE: f64 = 0.5;
+
+fn pair_percent(a: u8, b: u8) -> f64 {
+    let pair = [a.to_ascii_lowercase(), b.to_ascii_lowercase()];
+    ENGLISH_BIGRAMS
+        .iter()
+        .find(|(p, _)| p.as_bytes() == pair)
+        .map_or(RARE_PERCENT, |&(_, percent)| percent)
+}
+
+/// Mean log2 ratio of each letter pair's English frequency to a uniform
+/// one: positive for word-like text, negative for random letters. Word
+/// separators (`-_./` and lower-to-upper case changes) start a new word;
+/// pairs with a digit or another symbol count as rare. None if the token
+/// has too few pairs to judge.
+pub fn english_score(token: &str) -> Option<f64> {
+    let bytes = token.as_bytes();
+    let mut total = 0.0;
+    let mut pairs = 0;
+    for w in bytes.windows(2) {
+        let (a, b) = (w[0], w[1]);
+        let separator = |c: u8| matches!(c, b'-' | b'_' | b'.' | b'/');
+        if separator(a) || separator(b) || (a.is_ascii_lowercase() && b.is_ascii_uppercase()) {
+            continue;
+        }
+        let percent = if a.is_ascii_alphabetic() && b.is_ascii_alphabetic() {
+            pair_percent(a, b)
+        } else {
+            RARE_PERCENT
+        };
+        total += (percent / UNIFORM_PERCENT).log2();
+        pairs += 1;
+    }
+    (pairs >= MIN_PAIRS).then(|| total / pairs as f64)
+}
@@ -0,0 +1,86 @@
+// CSV findings report for --output=csv
+//
+// One row per finding under a `line,col,label,structure,source` header, for
+// sorting and filtering findings in a spreadsheet. Like the SARIF report it
+// only carries the marker structure, never the secret itself.
+
+use kahl::FilterKind;
+
+const HEADER: &str = "line,col,label,structure,source";
+
+/// One row: 1-based line and code point column of the finding
+struct CsvRow {
+    line: usize,
+    col: usize,
+    label: String,
+    structure: String,
+    source: &'static str,
+}
+
+/// Findings collected while the input is read, rendered at EOF
+#[derive(Default)]
+pub struct CsvReport {
+    rows: Vec<CsvRow>,
+}
+
+impl CsvReport {
+    /// Record a single-line finding; `start` is a byte offset into `line`
+    pub fn add(
+        &mut self,
+        line: &str,
+        line_no: usize,
+        label: &str,
+        structure: &str,
+        start: usize,
+        source: FilterKind,
+    ) {
+        self.rows.push(CsvRow {
+            line: line_no,
+            col: line[..start].chars().count() + 1,
+            label: label.to_string(),
+            structure: structure.to_string(),
+            source: match source {
+                FilterKind::Values => "values",
+                FilterKind::Patterns => "patterns",
+                FilterKind::Entropy => "entropy",
+            },
+        });
+    }
+
+    /// Record a private key block starting at `start_line`
+    pub fn add_block(&mut self, label: &str, structure: &str, start_line: usize) {
+        self.rows.push(CsvRow {
+            line: start_line,
+            col: 1,
+            label: label.to_string(),
+            structure: structure.to_string(),
+            source: "patterns",
+        });
+    }
+
+    /// Render the header and one row per finding
+    pub fn render(&self) -> String {
+        let mut out = format!("{}\n", HEADER);
+        for r in &self.rows {
+            out.push_str(&format!(
+                "{},{},{},{},{}\n",
+                r.line,
+                r.col,
+                csv_field(&r.label),
+                csv_field(&r.structure),
+                r.source
+            ));
+        }
+        out
+    }
+}
+
+/// A field as is, or quoted with inner quotes doubled when it contains a
+/// comma, quote or line break
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
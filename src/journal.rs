@@ -0,0 +1,87 @@
+// systemd journal export format for --journal
+//
+// Entries are FIELD=value lines ended by a blank line. Values that are not
+// plain text (newlines, control bytes) use the binary form: the field name on
+// its own line, a 64-bit little-endian length, the raw bytes and a newline.
+
+use std::io::{self, BufRead, Read};
+
+/// One unit of the export stream
+pub enum JournalItem {
+    /// A field; `binary` if it used the length-prefixed form
+    Field {
+        name: Vec<u8>,
+        value: Vec<u8>,
+        binary: bool,
+    },
+    /// Blank line ending an entry
+    EntryEnd,
+}
+
+/// Read the next item; `first` is a line already read from `input`
+pub fn read_item(
+    input: &mut impl BufRead,
+    first: Option<Vec<u8>>,
+) -> io::Result<Option<JournalItem>> {
+    let mut line = match first {
+        Some(line) => line,
+        None => {
+            let mut line = Vec::new();
+            if input.read_until(b'\n', &mut line)? == 0 {
+                return Ok(None);
+            }
+            line
+        }
+    };
+    if line.last() == Some(&b'\n') {
+        line.pop();
+    }
+    if line.is_empty() {
+        return Ok(Some(JournalItem::EntryEnd));
+    }
+
+    if let Some(eq) = line.iter().position(|&b| b == b'=') {
+        let value = line.split_off(eq + 1);
+        line.pop();
+        return Ok(Some(JournalItem::Field {
+            name: line,
+            value,
+            binary: false,
+        }));
+    }
+
+    // Binary field: length, data, trailing newline
+    let mut len = [0u8; 8];
+    input.read_exact(&mut len)?;
+    let len = u64::from_le_bytes(len);
+    let mut value = Vec::new();
+    input.take(len).read_to_end(&mut value)?;
+    if value.len() as u64 != len {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "truncated binary journal field",
+        ));
+    }
+    let mut newline = [0u8; 1];
+    input.read_exact(&mut newline)?;
+    Ok(Some(JournalItem::Field {
+        name: line,
+        value,
+        binary: true,
+    }))
+}
+
+/// Serialize a field; values containing a newline always use the binary form
+pub fn encode_field(name: &[u8], value: &[u8], binary: bool) -> Vec<u8> {
+    let mut out = Vec::with_capacity(name.len() + value.len() + 10);
+    out.extend_from_slice(name);
+    if binary || value.contains(&b'\n') {
+        out.push(b'\n');
+        out.extend_from_slice(&(value.len() as u64).to_le_bytes());
+    } else {
+        out.push(b'=');
+    }
+    out.extend_from_slice(value);
+    out.push(b'\n');
+    out
+}
@@ -0,0 +1,3241 @@
+//! kahl: streaming secret redaction
+//!
+//! The binary is a thin CLI around [`Redactor`]. Library users build one with
+//! [`RedactorBuilder`], optionally registering their own patterns, and call
+//! [`Redactor::redact_line`] on each line of input.
+//!
+//! ```no_run
+//! use kahl::RedactorBuilder;
+//!
+//! let redactor = RedactorBuilder::new()
+//!     .add_pattern("INTERNAL_TOKEN", r"itk_[a-z0-9]{32}")
+//!     .unwrap()
+//!     .build();
+//! let out = redactor.redact_line("token itk_0123456789abcdef0123456789abcdef");
+//! assert!(out.contains("[REDACTED:INTERNAL_TOKEN:"));
+//! ```
+//!
+//! [`RedactingReader`] wraps any `BufRead` and yields the redacted stream
+//! through `std::io::Read`, for code that expects a reader.
+//! [`Redactor::redact_chunk`] takes input in arbitrary pieces instead, for
+//! callers that read fixed-size chunks.
+
+mod bigram;
+mod patterns_gen;
+mod reader;
+mod sha256;
+use patterns_gen::*;
+
+pub use reader::RedactingReader;
+
+/// Helpers shared with the kahl binary; not part of the library API
+#[doc(hidden)]
+pub mod internal {
+    /// A line's terminator: `\r\n`, `\n`, or empty for a final unterminated line
+    pub fn line_ending(line: &str) -> &'static str {
+        if line.ends_with("\r\n") {
+            "\r\n"
+        } else if line.ends_with('\n') {
+            "\n"
+        } else {
+            ""
+        }
+    }
+}
+
+// Streaming state machine parameters
+pub use patterns_gen::{MAX_PRIVATE_KEY_BUFFER, PRIVATE_KEY_BEGIN, PRIVATE_KEY_END};
+
+use aho_corasick::{AhoCorasick, MatchKind};
+use regex::Regex;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::env;
+
+/// Check if a string value is falsy (0, false, no)
+pub fn is_falsy(val: &str) -> bool {
+    matches!(val.to_lowercase().as_str(), "0" | "false" | "no")
+}
+
+/// Check if a string value is truthy (1, true, yes)
+pub fn is_truthy(val: &str) -> bool {
+    matches!(val.to_lowercase().as_str(), "1" | "true" | "yes")
+}
+
+/// Which filters are active
+#[derive(Debug, Clone, Copy)]
+pub struct FilterConfig {
+    pub values: bool,
+    pub patterns: bool,
+    pub entropy: bool,
+}
+
+impl Default for FilterConfig {
+    fn default() -> Self {
+        Self {
+            values: true,
+            patterns: true,
+            entropy: ENTROPY_ENABLED_DEFAULT,
+        }
+    }
+}
+
+/// How the structure part of a marker is rendered
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StructureFormat {
+    /// Terse shape, e.g. `ghp_36X` or `alnum:200:5.5`
+    #[default]
+    Compact,
+    /// Parser-friendly `key=val` fields, e.g. `len=40 charset=hex`
+    Verbose,
+}
+
+/// What private key block markers show as their structure
+///
+/// With neither part the structure is `multiline`; otherwise the parts are
+/// joined like other structures: `RSA:27lines` (compact) or
+/// `type=RSA lines=27` (verbose).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PrivateKeyStructure {
+    /// Key type from the BEGIN line (`RSA`, `OPENSSH`; `PKCS8` for a bare
+    /// `PRIVATE KEY`)
+    pub key_type: bool,
+    /// Number of input lines in the block
+    pub lines: bool,
+}
+
+/// How much of a JWT the `JWT_TOKEN` pattern redacts
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum JwtMode {
+    /// The whole token
+    #[default]
+    Full,
+    /// Only the signature (third segment); header and payload stay readable
+    /// and are left alone by the other filters. The payload may hold
+    /// personal data, so this is opt-in
+    SignatureOnly,
+}
+
+/// Which part of an email address the opt-in `EMAIL` detection redacts
+///
+/// In URL userinfo (`https://alice@host/`) only the user part is redacted,
+/// whatever the mode, so the URL keeps its host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmailMode {
+    /// The whole address
+    Full,
+    /// The local part; `@domain` stays readable (e.g. for analytics)
+    Local,
+    /// The domain; `local@` stays readable
+    Domain,
+}
+
+/// Hash used in place of the structure when hashing is enabled
+///
+/// Equal secrets give equal markers, so occurrences can be correlated
+/// without revealing the secret or its shape.
+#[derive(Debug, Clone)]
+pub enum SecretHash {
+    /// Truncated SHA-256 of the secret (guessable for low-entropy secrets)
+    Sha256,
+    /// Truncated HMAC-SHA-256 keyed with a salt; not comparable across salts
+    Hmac(Vec<u8>),
+}
+
+/// Hex digits of the digest kept in `hash=` markers
+const HASH_HEX_LEN: usize = 12;
+
+impl SecretHash {
+    fn digest(&self, secret: &str) -> String {
+        let digest = match self {
+            SecretHash::Sha256 => sha256::sha256(secret.as_bytes()),
+            SecretHash::Hmac(salt) => sha256::hmac_sha256(salt, secret.as_bytes()),
+        };
+        sha256::hex_prefix(&digest, HASH_HEX_LEN)
+    }
+}
+
+/// Default minimum length of a context-pattern value to be redacted
+pub const DEFAULT_MIN_CONTEXT_LEN: usize = 4;
+
+/// Default decimals of the entropy score in entropy markers
+pub const DEFAULT_ENTROPY_PRECISION: usize = 1;
+
+/// Which filter produced a [`Finding`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FilterKind {
+    Values,
+    Patterns,
+    Entropy,
+}
+
+/// One redacted span, in byte offsets of the original (unredacted) line;
+/// with `normalize_unicode`, of that line after stripping invisible characters
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub label: String,
+    pub start: usize,
+    pub end: usize,
+    pub structure: String,
+    pub source: FilterKind,
+}
+
+/// A high-entropy candidate the entropy filter left alone, in byte offsets
+/// of the original line (see [`Redactor::entropy_skips`])
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntropySkip {
+    pub start: usize,
+    pub end: usize,
+    /// Compact entropy structure (`hex:40:3.8`); never the token itself
+    pub structure: String,
+    pub reason: SkipReason,
+}
+
+/// Why the entropy filter did not redact a token
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SkipReason {
+    /// Matched the exclusion with this label (`UUID`, `CONTEXT`, `ALLOWLIST`, ...)
+    Excluded(&'static str),
+    /// Scored this many bits below its charset's threshold
+    BelowThreshold(f64),
+    /// Above the threshold, but reads as English words under
+    /// [`EntropyModel::Bigram`] (score in bits per letter pair)
+    WordLike(f64),
+}
+
+impl std::fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SkipReason::Excluded(label) => write!(f, "excluded:{}", label),
+            SkipReason::BelowThreshold(delta) => write!(f, "below-threshold:-{:.2}", delta),
+            SkipReason::WordLike(score) => write!(f, "word-like:{:.2}", score),
+        }
+    }
+}
+
+/// Tokens scoring at most this many bits under their threshold are reported
+/// as near misses by [`Redactor::entropy_skips`]
+const ENTROPY_NEAR_MISS: f64 = 0.5;
+
+#[derive(Clone)]
+struct Pattern {
+    regex: Regex,
+    label: String,
+}
+
+#[derive(Clone)]
+struct ContextPattern {
+    regex: Regex,
+    label: String,
+    group: usize,
+}
+
+fn build_patterns() -> Vec<Pattern> {
+    PATTERNS
+        .iter()
+        .map(|(regex_str, label)| Pattern {
+            regex: Regex::new(regex_str).unwrap(),
+            label: label.to_string(),
+        })
+        .collect()
+}
+
+fn build_context_patterns() -> Vec<ContextPattern> {
+    CONTEXT_PATTERNS
+        .iter()
+        .map(|(regex_str, label, group)| ContextPattern {
+            regex: Regex::new(regex_str).unwrap(),
+            label: label.to_string(),
+            group: *group,
+        })
+        .collect()
+}
+
+// ============================================================================
+// Working text with offset tracking
+// ============================================================================
+
+/// Span of the working text and the original range it stands for
+///
+/// Verbatim pieces map byte-for-byte; marker pieces map as a whole.
+struct Piece {
+    out_start: usize,
+    out_end: usize,
+    orig_start: usize,
+    orig_end: usize,
+    marker: bool,
+}
+
+/// One replacement in working-text coordinates, rendered as a marker
+struct Edit {
+    start: usize,
+    end: usize,
+    label: String,
+    structure: String,
+}
+
+/// Share of the line (percent) above which a context value gets an opaque
+/// structure
+const OPAQUE_VALUE_PERCENT: usize = 80;
+
+/// How markers are rendered
+#[derive(Clone, Copy)]
+struct MarkerStyle<'a> {
+    format: StructureFormat,
+    hash: Option<&'a SecretHash>,
+    /// HMAC key for `fp=` fingerprints appended to structures
+    fingerprint: Option<&'a [u8]>,
+    /// Pad or shorten markers to the width of the text they replace
+    preserve_length: bool,
+    /// Render structures at all (off when only findings are wanted)
+    describe: bool,
+    /// Decimals of the entropy score in entropy markers; None omits it
+    entropy_precision: Option<usize>,
+    /// Shown before the label in every marker (e.g. a service name)
+    prefix: Option<&'a str>,
+    /// Names shown in markers instead of the given labels
+    label_map: &'a HashMap<String, String>,
+    /// Show a token's leading segment literally in compact structures
+    echo_prefix: bool,
+}
+
+/// A line mid-redaction: each pass rewrites `text`, and `pieces` keeps the
+/// mapping back to the original line so findings stay in its coordinates
+struct Redaction<'a> {
+    text: String,
+    /// Where `apply` builds the next text before copying it back into
+    /// `text`, so `text` keeps its allocation (the caller's, in place)
+    scratch: String,
+    orig: &'a str,
+    style: MarkerStyle<'a>,
+    /// Allow regexes (anchored): matching candidates are never redacted
+    allow: &'a [Regex],
+    pieces: Vec<Piece>,
+    findings: Vec<Finding>,
+    /// Entropy candidates left alone, collected only when Some
+    skips: Option<Vec<EntropySkip>>,
+    /// Original spans deliberately left readable: edits touching them are
+    /// dropped (JWT header and payload with `JwtMode::SignatureOnly`,
+    /// workflow command syntax)
+    kept: Vec<(usize, usize)>,
+    /// Digests of the values seen so far in the stream, when the first
+    /// occurrence of each is left readable (`keep_first_occurrence`)
+    first_seen: Option<&'a mut SeenValues>,
+}
+
+/// Default for [`RedactorBuilder::first_occurrence_cap`]
+pub const DEFAULT_FIRST_OCCURRENCE_CAP: usize = 100_000;
+
+/// Bytes [`Redactor::redact_chunk`] holds without a newline before it
+/// redacts up to the last token boundary
+pub const MAX_CHUNK_TAIL: usize = 64 * 1024;
+
+/// Digests of the distinct values seen in a stream (`keep_first_occurrence`);
+/// beyond `cap` the least recently seen one is forgotten
+struct SeenValues {
+    cap: usize,
+    /// Digest -> tick of its latest occurrence
+    last_seen: HashMap<[u8; 32], u64>,
+    /// Tick -> digest, least recently seen first
+    by_age: BTreeMap<u64, [u8; 32]>,
+    tick: u64,
+}
+
+impl SeenValues {
+    fn new(cap: usize) -> Self {
+        Self {
+            cap: cap.max(1),
+            last_seen: HashMap::new(),
+            by_age: BTreeMap::new(),
+            tick: 0,
+        }
+    }
+
+    /// Record an occurrence of `digest`; true if it was not remembered
+    fn insert(&mut self, digest: [u8; 32]) -> bool {
+        self.tick += 1;
+        let first = match self.last_seen.insert(digest, self.tick) {
+            Some(previous) => {
+                self.by_age.remove(&previous);
+                false
+            }
+            None => true,
+        };
+        self.by_age.insert(self.tick, digest);
+        if self.last_seen.len() > self.cap
+            && let Some((_, oldest)) = self.by_age.pop_first()
+        {
+            self.last_seen.remove(&oldest);
+        }
+        first
+    }
+}
+
+impl<'a> Redaction<'a> {
+    fn new(line: &'a str, style: MarkerStyle<'a>, allow: &'a [Regex]) -> Self {
+        Self::with_buffer(line, String::new(), style, allow)
+    }
+
+    /// Like `new`, writing the redacted text into `buf`'s allocation
+    fn with_buffer(
+        line: &'a str,
+        mut buf: String,
+        style: MarkerStyle<'a>,
+        allow: &'a [Regex],
+    ) -> Self {
+        buf.clear();
+        buf.push_str(line);
+        Self {
+            text: buf,
+            scratch: String::new(),
+            orig: line,
+            style,
+            allow,
+            pieces: vec![Piece {
+                out_start: 0,
+                out_end: line.len(),
+                orig_start: 0,
+                orig_end: line.len(),
+                marker: false,
+            }],
+            findings: Vec::new(),
+            skips: None,
+            kept: Vec::new(),
+            first_seen: None,
+        }
+    }
+
+    /// Structure of a pattern or value match in the configured format,
+    /// followed by its fingerprint if one is configured
+    fn structure(&self, secret: &str) -> String {
+        if !self.style.describe {
+            return String::new();
+        }
+        let structure = self.describe(secret);
+        self.with_fingerprint(structure, secret)
+    }
+
+    fn describe(&self, secret: &str) -> String {
+        if let Some(hash) = self.style.hash {
+            return format!("hash={}", hash.digest(secret.trim()));
+        }
+        match self.style.format {
+            StructureFormat::Compact => describe_structure(secret, self.style.echo_prefix),
+            StructureFormat::Verbose => format!(
+                "len={} charset={}",
+                secret.len(),
+                charset_abbrev(classify_charset(secret, &[]))
+            ),
+        }
+    }
+
+    /// Structure of a value captured after a key (context patterns, env
+    /// dumps, form bodies): opaque `Nchars` when the value is most of the
+    /// line, so no echoed segment reveals a large part of it
+    fn context_structure(&self, secret: &str) -> String {
+        let len = secret.chars().count();
+        let line_len = self.orig.trim_end_matches(['\r', '\n']).chars().count();
+        if len * 100 > line_len * OPAQUE_VALUE_PERCENT {
+            self.opaque_structure(secret)
+        } else {
+            self.structure(secret)
+        }
+    }
+
+    /// Structure that echoes nothing of the value: `Nchars` in the compact
+    /// format, otherwise as configured
+    fn opaque_structure(&self, secret: &str) -> String {
+        if self.style.describe
+            && self.style.hash.is_none()
+            && self.style.format == StructureFormat::Compact
+        {
+            self.with_fingerprint(format!("{}chars", secret.chars().count()), secret)
+        } else {
+            self.structure(secret)
+        }
+    }
+
+    /// Structure of an entropy match in the configured format
+    fn entropy_structure(&self, token: &str, entropy: f64, charset: &str) -> String {
+        if !self.style.describe || self.style.hash.is_some() {
+            return self.structure(token);
+        }
+        let structure = match self.style.format {
+            StructureFormat::Compact => {
+                describe_entropy_structure(token, entropy, charset, self.style.entropy_precision)
+            }
+            StructureFormat::Verbose => {
+                let mut s = format!("len={} charset={}", token.len(), charset_abbrev(charset));
+                if let Some(precision) = self.style.entropy_precision {
+                    s.push_str(&format!(" entropy={:.*}", precision, entropy));
+                }
+                s
+            }
+        };
+        self.with_fingerprint(structure, token)
+    }
+
+    /// Append `:fp=` and the keyed HMAC of the secret (`--fingerprint`)
+    fn with_fingerprint(&self, structure: String, secret: &str) -> String {
+        match self.style.fingerprint {
+            Some(key) => {
+                let digest = sha256::hmac_sha256(key, secret.trim().as_bytes());
+                format!(
+                    "{}:fp={}",
+                    structure,
+                    sha256::hex_prefix(&digest, HASH_HEX_LEN)
+                )
+            }
+            None => structure,
+        }
+    }
+
+    /// Original offset for a start position in the working text
+    fn orig_start(&self, pos: usize) -> usize {
+        match self.pieces.iter().find(|p| pos < p.out_end) {
+            Some(p) if p.marker => p.orig_start,
+            Some(p) => p.orig_start + pos.saturating_sub(p.out_start),
+            None => self.orig.len(),
+        }
+    }
+
+    /// Original offset for an end position in the working text
+    fn orig_end(&self, pos: usize) -> usize {
+        match self.pieces.iter().rev().find(|p| pos > p.out_start) {
+            Some(p) if p.marker => p.orig_end,
+            Some(p) => p.orig_start + (pos - p.out_start).min(p.out_end - p.out_start),
+            None => 0,
+        }
+    }
+
+    /// Copy working text `[from, to)` into `text`, carrying its pieces along
+    fn copy_range(&self, from: usize, to: usize, text: &mut String, pieces: &mut Vec<Piece>) {
+        if from >= to {
+            return;
+        }
+        for p in &self.pieces {
+            let (a, b) = (p.out_start.max(from), p.out_end.min(to));
+            if a >= b {
+                continue;
+            }
+            let (orig_start, orig_end) = if p.marker {
+                (p.orig_start, p.orig_end)
+            } else {
+                (
+                    p.orig_start + (a - p.out_start),
+                    p.orig_start + (b - p.out_start),
+                )
+            };
+            pieces.push(Piece {
+                out_start: text.len() + (a - from),
+                out_end: text.len() + (b - from),
+                orig_start,
+                orig_end,
+                marker: p.marker,
+            });
+        }
+        text.push_str(&self.text[from..to]);
+    }
+
+    /// Whether working text `[start, end)` is made up of markers only
+    fn only_markers(&self, start: usize, end: usize) -> bool {
+        start < end
+            && self
+                .pieces
+                .iter()
+                .filter(|p| p.out_start < end && p.out_end > start)
+                .all(|p| p.marker)
+    }
+
+    /// Whether an edit overlaps a span that must stay readable
+    fn kept(&self, e: &Edit) -> bool {
+        if self.kept.is_empty() {
+            return false;
+        }
+        let (start, end) = (self.orig_start(e.start), self.orig_end(e.end));
+        self.kept.iter().any(|&(a, b)| start < b && end > a)
+    }
+
+    /// Whether an edit is the first occurrence of its value in the stream,
+    /// with `keep_first_occurrence`: it is then recorded and kept readable,
+    /// also from later filters
+    fn first_occurrence(&mut self, e: &Edit) -> bool {
+        let (start, end) = (self.orig_start(e.start), self.orig_end(e.end));
+        let Some(seen) = self.first_seen.as_deref_mut() else {
+            return false;
+        };
+        if !seen.insert(sha256::sha256(&self.orig.as_bytes()[start..end])) {
+            return false;
+        }
+        self.kept.push((start, end));
+        true
+    }
+
+    /// Whether an edit is exempted by an allow regex: either the candidate
+    /// itself or the whitespace/quote-delimited word around it matches
+    fn allowed(&self, e: &Edit) -> bool {
+        if self.allow.is_empty() {
+            return false;
+        }
+        let is_delim = |c: char| c.is_whitespace() || matches!(c, '"' | '\'' | '`');
+        let word_start = self.text[..e.start]
+            .char_indices()
+            .rev()
+            .find(|&(_, c)| is_delim(c))
+            .map_or(0, |(i, c)| i + c.len_utf8());
+        let word_end = self.text[e.end..]
+            .find(is_delim)
+            .map_or(self.text.len(), |i| e.end + i);
+        let candidate = self.text[e.start..e.end].trim();
+        let word = &self.text[word_start..word_end];
+        self.allow
+            .iter()
+            .any(|re| re.is_match(candidate) || re.is_match(word))
+    }
+
+    /// Replace non-overlapping edits with markers and record their findings
+    ///
+    /// Edits that would only re-redact existing markers are dropped, so the
+    /// first filter to redact a secret keeps its label. Allowed candidates
+    /// are dropped too.
+    fn apply(&mut self, mut edits: Vec<Edit>, source: FilterKind) {
+        edits.retain(|e| !self.only_markers(e.start, e.end) && !self.allowed(e) && !self.kept(e));
+        if self.first_seen.is_some() {
+            edits.retain(|e| !self.first_occurrence(e));
+        }
+        if edits.is_empty() {
+            return;
+        }
+        edits.sort_by_key(|e| e.start);
+
+        let mut text = std::mem::take(&mut self.scratch);
+        text.clear();
+        text.reserve(self.text.len());
+        let mut pieces = Vec::with_capacity(self.pieces.len() + 2 * edits.len());
+        let mut last = 0;
+        for e in edits {
+            self.copy_range(last, e.start, &mut text, &mut pieces);
+
+            let orig_start = self.orig_start(e.start);
+            let orig_end = self.orig_end(e.end).max(orig_start);
+            let label = self.marker_label(&e.label);
+            let marker = if self.style.preserve_length {
+                let width = self.orig[orig_start..orig_end].chars().count();
+                fixed_width_marker(&label, &e.structure, width)
+            } else {
+                format!("[REDACTED:{}:{}]", label, e.structure)
+            };
+            pieces.push(Piece {
+                out_start: text.len(),
+                out_end: text.len() + marker.len(),
+                orig_start,
+                orig_end,
+                marker: true,
+            });
+            text.push_str(&marker);
+
+            // An earlier finding swallowed by this marker no longer shows in the output
+            self.findings
+                .retain(|f| f.start < orig_start || f.end > orig_end || f.start == f.end);
+            self.findings.push(Finding {
+                label: e.label,
+                start: orig_start,
+                end: orig_end,
+                structure: e.structure,
+                source,
+            });
+            last = e.end;
+        }
+        self.copy_range(last, self.text.len(), &mut text, &mut pieces);
+
+        self.text.clear();
+        self.text.push_str(&text);
+        self.scratch = text;
+        self.pieces = pieces;
+    }
+
+    /// Label as shown in markers: mapped, after the marker prefix
+    fn marker_label(&self, label: &str) -> String {
+        let shown = self
+            .style
+            .label_map
+            .get(label)
+            .map_or(label, String::as_str);
+        match self.style.prefix {
+            Some(prefix) => format!("{}:{}", prefix, shown),
+            None => shown.to_string(),
+        }
+    }
+
+    /// Collapse runs of markers with the same label, separated only by a
+    /// short delimiter, into one `[REDACTED:LABEL:xN]` (`--merge-adjacent`)
+    ///
+    /// Findings are kept per secret. Must be the last pass: pieces are not
+    /// updated.
+    fn merge_adjacent(&mut self) {
+        let markers: Vec<(usize, usize, Option<&str>)> = self
+            .pieces
+            .iter()
+            .filter(|p| p.marker)
+            .map(|p| {
+                let finding = self
+                    .findings
+                    .iter()
+                    .find(|f| f.start == p.orig_start && f.end == p.orig_end);
+                (p.out_start, p.out_end, finding.map(|f| f.label.as_str()))
+            })
+            .collect();
+
+        let mut runs = Vec::new();
+        let mut i = 0;
+        while i < markers.len() {
+            let (start, mut end, label) = markers[i];
+            let mut count = 1;
+            while let Some(&(next_start, next_end, next_label)) = markers.get(i + count)
+                && label.is_some()
+                && next_label == label
+                && is_merge_delimiter(&self.text[end..next_start])
+            {
+                end = next_end;
+                count += 1;
+            }
+            if let Some(label) = label.filter(|_| count > 1) {
+                runs.push((start, end, label.to_string(), count));
+            }
+            i += count;
+        }
+
+        for (start, end, label, count) in runs.into_iter().rev() {
+            let marker = format!("[REDACTED:{}:x{}]", self.marker_label(&label), count);
+            self.text.replace_range(start..end, &marker);
+        }
+    }
+}
+
+/// Text between two markers that `--merge-adjacent` may swallow: nothing,
+/// or up to three commas, semicolons, pipes and blanks
+fn is_merge_delimiter(s: &str) -> bool {
+    s.len() <= 3 && s.chars().all(|c| matches!(c, ',' | ';' | '|' | ' ' | '\t'))
+}
+
+/// Marker exactly `width` chars wide where possible (`--preserve-length`)
+///
+/// Uses the most detailed of `[REDACTED:LABEL:structure]`, `[REDACTED:LABEL]`
+/// and `[REDACTED]` that fits and pads it with `*` before the closing bracket.
+/// Spans narrower than `[REDACTED]` still get the full base marker.
+fn fixed_width_marker(label: &str, structure: &str, width: usize) -> String {
+    let candidates = [
+        format!("[REDACTED:{}:{}", label, structure),
+        format!("[REDACTED:{}", label),
+        "[REDACTED".to_string(),
+    ];
+    let base = candidates
+        .iter()
+        .find(|c| c.chars().count() < width)
+        .unwrap_or(&candidates[2]);
+    let fill = width.saturating_sub(base.chars().count() + 1);
+    format!("{}{}]", base, "*".repeat(fill))
+}
+
+fn classify_segment(s: &str) -> String {
+    if s.is_empty() {
+        return String::new();
+    }
+    if s.chars().all(|c| c.is_ascii_digit()) {
+        return format!("{}N", s.len());
+    }
+    if s.chars().all(|c| c.is_ascii_alphabetic()) {
+        return format!("{}A", s.len());
+    }
+    format!("{}X", s.len())
+}
+
+/// Compact structure of a secret, e.g. `xoxb-9N-12X` or `sk-...:105chars`
+///
+/// With `echo_prefix` off, the leading segment is classified like the rest
+/// (`4A-9N-12X`) instead of shown literally.
+fn describe_structure(s: &str, echo_prefix: bool) -> String {
+    if s.is_empty() {
+        return String::new();
+    }
+    let show = |first: &str| {
+        if echo_prefix {
+            first.to_string()
+        } else {
+            classify_segment(first)
+        }
+    };
+
+    // Very long tokens
+    if s.len() >= LONG_THRESHOLD {
+        for sep in ["-", "_", "."] {
+            if s.contains(sep) {
+                let parts: Vec<&str> = s.split(sep).collect();
+                let first = parts[0];
+                let is_alpha = first.chars().all(|c| c.is_ascii_alphabetic());
+                let known_prefixes = ["ghp", "gho", "ghs", "ghr", "npm", "sk"];
+                if is_alpha || known_prefixes.contains(&first) {
+                    return format!("{}{}...:{}chars", show(first), sep, s.len());
+                }
+            }
+        }
+        return format!("{}chars", s.len());
+    }
+
+    // Check for structured tokens
+    for sep in ["-", ".", "_"] {
+        if s.contains(sep) {
+            let parts: Vec<&str> = s.split(sep).collect();
+            if parts.len() >= 2 {
+                let first = parts[0];
+                let is_alpha = first.chars().all(|c| c.is_ascii_alphabetic());
+                if is_alpha && first.len() <= 12 {
+                    let segments: Vec<String> =
+                        parts[1..].iter().map(|p| classify_segment(p)).collect();
+                    return format!("{}{}{}", show(first), sep, segments.join(sep));
+                }
+                let segments: Vec<String> = parts.iter().map(|p| classify_segment(p)).collect();
+                return segments.join(sep);
+            }
+        }
+    }
+
+    classify_segment(s)
+}
+
+/// Whether an env var name denotes a secret (explicit list or suffix, see
+/// `patterns/env.yaml`)
+fn is_sensitive_env_name(name: &str) -> bool {
+    EXPLICIT_ENV_VARS.contains(&name) || ENV_SUFFIXES.iter().any(|p| name.ends_with(p))
+}
+
+fn load_secrets() -> HashMap<String, String> {
+    let mut secrets = HashMap::new();
+
+    for (name, value) in env::vars() {
+        if value.len() < 8 {
+            continue;
+        }
+
+        if is_sensitive_env_name(&name) {
+            secrets.insert(name, value);
+        }
+    }
+
+    secrets
+}
+
+/// A `NAME=value` line as printed by `env`/`printenv` (`--env-dump`)
+const ENV_DUMP_PATTERN: &str = r"^([A-Z_][A-Z0-9_]*)=([^\r\n]*)";
+
+/// Redact the value of a dumped env assignment whose name is sensitive,
+/// labeled with the name like the process's own env values
+fn redact_env_dump(
+    r: &mut Redaction,
+    line_re: &Regex,
+    enabled: impl Fn(&str) -> bool,
+    min_context_len: usize,
+) {
+    let Some(caps) = line_re.captures(&r.text) else {
+        return;
+    };
+    let (name, value) = (&caps[1], caps.get(2).unwrap());
+    if !is_sensitive_env_name(name)
+        || !enabled(name)
+        || value.as_str().chars().count() < min_context_len.max(1)
+    {
+        return;
+    }
+    let edit = Edit {
+        start: value.start(),
+        end: value.end(),
+        label: name.to_string(),
+        structure: r.context_structure(value.as_str()),
+    };
+    r.apply(vec![edit], FilterKind::Values);
+}
+
+/// How secret values are found in a line, compiled once at build time
+enum SecretMatcher {
+    /// Any occurrence, including inside longer words
+    Substring(AhoCorasick),
+    /// Only occurrences not flanked by word characters (`whole_word_secrets`)
+    WholeWord(Regex),
+}
+
+/// Secret values compiled into one matcher, with the env var name of each
+struct SecretValues {
+    matcher: SecretMatcher,
+    /// Names in pattern order (substring matcher)
+    names: Vec<String>,
+    /// Names by value (whole-word matcher, which reports only the span)
+    names_by_value: HashMap<String, String>,
+}
+
+/// Compile secret values (None if there are none)
+///
+/// All occurrences are replaced in one left-to-right pass, preferring the
+/// longest secret at a position: a secret contained in a longer one, or
+/// overlapping another, can't split the match. Duplicate values keep the
+/// alphabetically first name.
+fn build_secret_values(secrets: HashMap<String, String>, whole_word: bool) -> Option<SecretValues> {
+    let mut secrets: Vec<(String, String)> =
+        secrets.into_iter().filter(|(_, v)| !v.is_empty()).collect();
+    secrets.sort();
+    secrets.dedup_by(|b, a| a.1 == b.1);
+    if secrets.is_empty() {
+        return None;
+    }
+
+    if whole_word {
+        // Leftmost-first alternation: longest values first so they win ties.
+        // \b only where the value starts/ends with a word character, so
+        // values like "-x-" still match next to a space
+        let mut values: Vec<&str> = secrets.iter().map(|(_, v)| v.as_str()).collect();
+        values.sort_by_key(|v| std::cmp::Reverse(v.len()));
+        let is_word = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == '_');
+        let alternation: Vec<String> = values
+            .iter()
+            .map(|v| {
+                format!(
+                    "{}{}{}",
+                    if is_word(v.chars().next()) { r"\b" } else { "" },
+                    regex::escape(v),
+                    if is_word(v.chars().last()) { r"\b" } else { "" }
+                )
+            })
+            .collect();
+        let matcher = regex::RegexBuilder::new(&alternation.join("|"))
+            .size_limit(1 << 28)
+            .build()
+            .unwrap();
+        return Some(SecretValues {
+            matcher: SecretMatcher::WholeWord(matcher),
+            names: Vec::new(),
+            names_by_value: secrets.into_iter().map(|(n, v)| (v, n)).collect(),
+        });
+    }
+
+    let matcher = AhoCorasick::builder()
+        .match_kind(MatchKind::LeftmostLongest)
+        .build(secrets.iter().map(|(_, v)| v))
+        .unwrap();
+    Some(SecretValues {
+        matcher: SecretMatcher::Substring(matcher),
+        names: secrets.into_iter().map(|(n, _)| n).collect(),
+        names_by_value: HashMap::new(),
+    })
+}
+
+fn redact_env_values(r: &mut Redaction, secrets: &SecretValues) {
+    let edit = |start: usize, end: usize, name: &str| Edit {
+        start,
+        end,
+        label: name.to_string(),
+        structure: r.structure(&r.text[start..end]),
+    };
+    let edits = match &secrets.matcher {
+        SecretMatcher::Substring(ac) => ac
+            .find_iter(r.text.as_str())
+            .map(|m| edit(m.start(), m.end(), &secrets.names[m.pattern().as_usize()]))
+            .collect(),
+        SecretMatcher::WholeWord(re) => re
+            .find_iter(&r.text)
+            .map(|m| edit(m.start(), m.end(), &secrets.names_by_value[m.as_str()]))
+            .collect(),
+    };
+    r.apply(edits, FilterKind::Values);
+}
+
+/// Precompiled special patterns for hot path (None if the label is disabled)
+struct SpecialPatterns {
+    git_credential: Option<Regex>,
+    docker_auth: Option<Regex>,
+}
+
+fn build_special_patterns(enabled: impl Fn(&str) -> bool) -> SpecialPatterns {
+    let build = |sp: &SpecialPattern| {
+        if enabled(sp.label) {
+            Some(Regex::new(sp.pattern).unwrap())
+        } else {
+            None
+        }
+    };
+    SpecialPatterns {
+        git_credential: build(&GIT_CREDENTIAL_PATTERN),
+        docker_auth: build(&DOCKER_AUTH_PATTERN),
+    }
+}
+
+/// Label for values of sensitive keys in form bodies (`--form-aware`)
+const FORM_SECRET_LABEL: &str = "FORM_SECRET";
+
+/// Precompiled regexes for form-aware redaction
+struct FormPatterns {
+    /// A run of `key=value` pairs joined by `&`
+    body: Regex,
+    /// Decoded key names whose values are secrets
+    sensitive_key: Regex,
+}
+
+fn build_form_patterns() -> FormPatterns {
+    FormPatterns {
+        body: Regex::new(r#"[A-Za-z0-9_.~%\[\]-]+=[^&\s"']*(?:&[A-Za-z0-9_.~%\[\]-]+=[^&\s"']*)+"#)
+            .unwrap(),
+        sensitive_key: Regex::new(
+            r"(?i-u:password|passwd|pwd|secret|token|api_?key|credential|assertion|code_verifier)",
+        )
+        .unwrap(),
+    }
+}
+
+/// Decode a form-encoded key (`+` is a space, `%XX` a byte)
+fn form_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let hex = |b: u8| (b as char).to_digit(16);
+                match (hex(bytes[i + 1]), hex(bytes[i + 2])) {
+                    (Some(hi), Some(lo)) => {
+                        out.push((hi * 16 + lo) as u8);
+                        i += 2;
+                    }
+                    _ => out.push(b'%'),
+                }
+            }
+            b => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Whether a decoded form key names a secret (`token_type` and `*_hint` are metadata)
+fn is_sensitive_form_key(form: &FormPatterns, key: &str) -> bool {
+    let key = key.to_ascii_lowercase();
+    form.sensitive_key.is_match(&key) && !key.ends_with("_type") && !key.ends_with("_hint")
+}
+
+/// Redact values of sensitive keys in form bodies; returns the body spans so
+/// the generic `key=value` context patterns can leave them alone
+fn redact_form_bodies(
+    r: &mut Redaction,
+    form: &FormPatterns,
+    min_context_len: usize,
+) -> Vec<(usize, usize)> {
+    let mut edits = Vec::new();
+    for body in form.body.find_iter(&r.text) {
+        let mut pos = body.start();
+        for pair in body.as_str().split('&') {
+            if let Some((key, value)) = pair.split_once('=')
+                && value.chars().count() >= min_context_len
+                && is_sensitive_form_key(form, &form_decode(key))
+            {
+                let start = pos + key.len() + 1;
+                edits.push(Edit {
+                    start,
+                    end: start + value.len(),
+                    label: FORM_SECRET_LABEL.to_string(),
+                    structure: r.context_structure(value),
+                });
+            }
+            pos += pair.len() + 1;
+        }
+    }
+    r.apply(edits, FilterKind::Patterns);
+
+    // Markers fit the value syntax, so the bodies are found again after rewriting
+    form.body
+        .find_iter(&r.text)
+        .map(|m| (m.start(), m.end()))
+        .collect()
+}
+
+/// Label for JSON string values of sensitive keys
+const JSON_SECRET_LABEL: &str = "JSON_SECRET";
+
+/// Sensitive JSON key ending a line, its value on the next (pretty-printed
+/// JSON); same key list as the single-line JSON_SECRET pattern
+const JSON_KEY_AT_EOL: &str =
+    r#""[^"]*(?i-u:password|passwd|secret|token|apikey|api_key)(?:[_-][^"]*)?"\s*:\s*$"#;
+
+/// String value opening a line
+const JSON_VALUE_AT_BOL: &str = r#"^\s*"((?:[^"\\]|\\.)+)""#;
+
+/// Label for Kubernetes env `value:` entries of sensitive names
+const K8S_ENV_SECRET_LABEL: &str = "K8S_ENV_SECRET";
+
+/// `- name: NAME` of a container env entry, its `value:` on the next line
+const K8S_ENV_NAME: &str = r#"^\s*-\s+name:\s*["']?([A-Za-z_][A-Za-z0-9_]*)["']?\s*$"#;
+
+/// `value:` entry opening a line
+const K8S_ENV_VALUE: &str = r#"^\s*value:\s*["']?([^"'\r\n]+?)["']?\s*$"#;
+
+/// Whether a Kubernetes env name denotes a secret: a sensitive env var name,
+/// or a bare suffix such as `API_KEY` or `TOKEN`
+fn is_sensitive_k8s_env_name(name: &str) -> bool {
+    is_sensitive_env_name(name)
+        || ENV_SUFFIXES
+            .iter()
+            .any(|s| s.strip_prefix('_') == Some(name))
+}
+
+/// A value expected at the start of the next stream line
+#[derive(Clone, Copy, PartialEq)]
+enum Continuation {
+    None,
+    /// A sensitive JSON key ended the previous line
+    JsonValue,
+    /// The previous line named a sensitive Kubernetes env var
+    K8sEnvValue,
+    /// A `redact_between` fence opened on an earlier line is still open
+    Fenced,
+}
+
+/// Label for `password=` in the client groups of MySQL option files
+const MYCNF_PASSWORD_LABEL: &str = "MYCNF_PASSWORD";
+
+/// Option file group header (`[client]`, `[mysqld]`, ...)
+const MYCNF_GROUP: &str = r"^\s*\[([^\]\r\n]+)\]\s*$";
+
+/// Groups read by the MySQL and MariaDB client programs
+const MYCNF_CLIENT_GROUPS: &[&str] = &[
+    "client",
+    "client-server",
+    "client-mariadb",
+    "mariadb-client",
+    "mysql",
+    "mysqladmin",
+    "mysqlbinlog",
+    "mysqlcheck",
+    "mysqldump",
+    "mysqlimport",
+    "mysqlpump",
+    "mysqlshow",
+    "mysqlslap",
+];
+
+/// `password=` option, quoted or bare (a bare value ends at a `#` comment)
+const MYCNF_PASSWORD: &str = r#"^\s*(?i-u:password)\s*=\s*["']?([^"'\r\n#]*[^\s"'#])"#;
+
+/// Redact the value captured by `value_re` on a line whose meaning comes
+/// from earlier lines (a JSON value, a Kubernetes env value, a password in
+/// a MySQL client group)
+fn redact_continuation(r: &mut Redaction, value_re: &Regex, label: &str, min_context_len: usize) {
+    let Some(value) = value_re.captures(&r.text).and_then(|c| c.get(1)) else {
+        return;
+    };
+    if value.as_str().chars().count() < min_context_len {
+        return;
+    }
+    let edit = Edit {
+        start: value.start(),
+        end: value.end(),
+        label: label.to_string(),
+        structure: r.context_structure(value.as_str()),
+    };
+    r.apply(vec![edit], FilterKind::Patterns);
+}
+
+/// Invisible characters that can split a token without changing how it
+/// reads: zero-width spaces and joiners, bidi marks and embeddings, the
+/// word joiner and invisible operators, the BOM, soft hyphen and combining
+/// grapheme joiner
+fn is_invisible(c: char) -> bool {
+    matches!(
+        c,
+        '\u{00AD}'
+            | '\u{034F}'
+            | '\u{180E}'
+            | '\u{200B}'..='\u{200F}'
+            | '\u{202A}'..='\u{202E}'
+            | '\u{2060}'..='\u{2064}'
+            | '\u{2066}'..='\u{2069}'
+            | '\u{FEFF}'
+    )
+}
+
+/// `line` without zero-width and other invisible format characters, None
+/// if it has none (see [`RedactorBuilder::normalize_unicode`])
+pub fn strip_invisible(line: &str) -> Option<String> {
+    line.contains(is_invisible)
+        .then(|| line.chars().filter(|&c| !is_invisible(c)).collect())
+}
+
+/// Label for text between `redact_between` sentinels
+const FENCED_LABEL: &str = "FENCED";
+
+/// Spans of `line` between `start` and `end` sentinels (sentinels excluded),
+/// the first from the line start if a fence is `open` from an earlier line.
+/// A fence without its end runs to the end of the line (its line ending
+/// excluded) and is reported as still open.
+fn fenced_spans(line: &str, start: &str, end: &str, mut open: bool) -> (Vec<(usize, usize)>, bool) {
+    let body = line.trim_end_matches(['\r', '\n']);
+    let mut spans = Vec::new();
+    let mut pos = 0;
+    loop {
+        if open {
+            match body[pos..].find(end) {
+                Some(i) => {
+                    spans.push((pos, pos + i));
+                    pos += i + end.len();
+                    open = false;
+                }
+                None => {
+                    spans.push((pos, body.len()));
+                    break;
+                }
+            }
+        } else {
+            match body[pos..].find(start) {
+                Some(i) => {
+                    pos += i + start.len();
+                    open = true;
+                }
+                None => break,
+            }
+        }
+    }
+    spans.retain(|&(s, e)| s < e);
+    (spans, open)
+}
+
+/// Redact everything between the fence sentinels, whatever it looks like
+fn redact_fenced(r: &mut Redaction, fence: &(String, String), open: bool) {
+    let (spans, _) = fenced_spans(r.orig, &fence.0, &fence.1, open);
+    let edits = spans
+        .into_iter()
+        .map(|(start, end)| Edit {
+            start,
+            end,
+            label: FENCED_LABEL.to_string(),
+            structure: r.context_structure(&r.orig[start..end]),
+        })
+        .collect();
+    r.apply(edits, FilterKind::Patterns);
+}
+
+/// GitHub Actions workflow command (`::name params::message`): the name and
+/// the `::` before the message; params and message are filtered as usual
+const WORKFLOW_COMMAND_PATTERN: &str = r"^\s*::([a-z][a-z-]*)(?: [^\r\n]*?)?(::)";
+
+/// Keep the syntax of a workflow command line readable, so runner commands
+/// such as `::add-mask::` stay recognizable after redaction
+fn keep_workflow_command(r: &mut Redaction, command_re: &Regex) {
+    if let Some(c) = command_re.captures(r.orig) {
+        let (name, sep) = (c.get(1).unwrap(), c.get(2).unwrap());
+        r.kept.push((0, name.end()));
+        r.kept.push((sep.start(), sep.end()));
+    }
+}
+
+const JWT_LABEL: &str = "JWT_TOKEN";
+
+/// `JwtMode::SignatureOnly`: redact the signature of each JWT and keep its
+/// header and payload out of reach of the filters that follow
+fn redact_jwt_signatures(r: &mut Redaction, jwt_re: &Regex) {
+    let mut edits = Vec::new();
+    for m in jwt_re.find_iter(&r.text) {
+        // The pattern ends with `.signature`
+        let sig = m.start() + m.as_str().rfind('.').map_or(0, |i| i + 1);
+        r.kept.push((r.orig_start(m.start()), r.orig_start(sig)));
+        edits.push(Edit {
+            start: sig,
+            end: m.end(),
+            label: JWT_LABEL.to_string(),
+            structure: r.structure(&r.text[sig..m.end()]),
+        });
+    }
+    r.apply(edits, FilterKind::Patterns);
+}
+
+/// Candidate tokens for reversed-secret detection (`--detect-obfuscated`)
+const OBFUSCATED_TOKEN_PATTERN: &str = r"[A-Za-z0-9_-]{20,}";
+
+/// Redact tokens whose reversed form matches a direct pattern
+fn redact_reversed(r: &mut Redaction, patterns: &[Pattern], token_re: &Regex) {
+    let mut edits = Vec::new();
+    for token in token_re.find_iter(&r.text) {
+        let reversed: String = token.as_str().chars().rev().collect();
+        if let Some((p, m)) = patterns
+            .iter()
+            .find_map(|p| p.regex.find(&reversed).map(|m| (p, m)))
+        {
+            edits.push(Edit {
+                start: token.start(),
+                end: token.end(),
+                label: p.label.clone(),
+                structure: format!("reversed:{}", r.structure(m.as_str())),
+            });
+        }
+    }
+    r.apply(edits, FilterKind::Patterns);
+}
+
+fn redact_patterns(
+    r: &mut Redaction,
+    patterns: &[Pattern],
+    context_patterns: &[ContextPattern],
+    special: &SpecialPatterns,
+    form: Option<&FormPatterns>,
+    obfuscated_token_re: Option<&Regex>,
+    min_context_len: usize,
+) {
+    // Direct patterns
+    for p in patterns {
+        let edits = p
+            .regex
+            .find_iter(&r.text)
+            .map(|m| Edit {
+                start: m.start(),
+                end: m.end(),
+                label: p.label.clone(),
+                structure: r.structure(m.as_str()),
+            })
+            .collect();
+        r.apply(edits, FilterKind::Patterns);
+    }
+
+    // Reversed tokens (opt-in): plain matches are already markers by now
+    if let Some(token_re) = obfuscated_token_re {
+        redact_reversed(r, patterns, token_re);
+    }
+
+    // Form bodies (a=1&b=2): only sensitive keys' values, pair by pair
+    let form_spans = match form {
+        Some(form) => redact_form_bodies(r, form, min_context_len),
+        None => Vec::new(),
+    };
+
+    // Context patterns (simulate lookbehind): keep group 1 and anything after
+    // the secret group, redact in between.
+    // Trivially short values (token=1, secret: no) are left alone, and an
+    // empty or blank capture never becomes a marker, whatever the minimum.
+    for cp in context_patterns {
+        let edits = cp
+            .regex
+            .captures_iter(&r.text)
+            .filter_map(|caps| {
+                let whole = caps.get(0).unwrap();
+                let secret = caps.get(cp.group)?;
+                if secret.as_str().trim().chars().count() < min_context_len.max(1) {
+                    return None;
+                }
+                let start = caps.get(1).map_or(whole.start(), |m| m.end());
+                if form_spans.iter().any(|&(a, b)| a <= start && start < b) {
+                    return None;
+                }
+                Some(Edit {
+                    start,
+                    end: secret.end(),
+                    label: cp.label.clone(),
+                    structure: r.context_structure(secret.as_str()),
+                })
+            })
+            .collect();
+        r.apply(edits, FilterKind::Patterns);
+    }
+
+    // Git credential URLs: ://user:password@ -> ://user:[REDACTED]@
+    // Docker config auth: "auth": "base64" -> "auth": "[REDACTED]"
+    let specials = [
+        (&special.git_credential, &GIT_CREDENTIAL_PATTERN),
+        (&special.docker_auth, &DOCKER_AUTH_PATTERN),
+    ];
+    for (re, sp) in specials {
+        let Some(re) = re else { continue };
+        let edits = re
+            .captures_iter(&r.text)
+            .filter_map(|caps| caps.get(sp.secret_group))
+            .map(|m| Edit {
+                start: m.start(),
+                end: m.end(),
+                label: sp.label.to_string(),
+                structure: r.context_structure(m.as_str()),
+            })
+            .collect();
+        r.apply(edits, FilterKind::Patterns);
+    }
+}
+
+/// Label for email addresses (`RedactorBuilder::redact_emails`)
+const EMAIL_LABEL: &str = "EMAIL";
+
+/// Email address: local part (group 1) and domain (group 2). A `mailto:`
+/// scheme or `?subject=` query around it is not part of the match.
+const EMAIL_PATTERN: &str =
+    r"([A-Za-z0-9._%+-]+)@((?:[A-Za-z0-9](?:[A-Za-z0-9-]*[A-Za-z0-9])?\.)+[A-Za-z]{2,})\b";
+
+/// Redact email addresses, or the part of them `mode` names
+fn redact_emails(r: &mut Redaction, email_re: &Regex, mode: EmailMode) {
+    let edits = email_re
+        .captures_iter(&r.text)
+        .map(|caps| {
+            let (local, domain) = (caps.get(1).unwrap(), caps.get(2).unwrap());
+            // user@host right after scheme:// is URL userinfo, not an address
+            let word_start = r.text[..local.start()]
+                .rfind(|c: char| c.is_whitespace() || matches!(c, '"' | '\'' | '<' | '('))
+                .map_or(0, |i| i + 1);
+            let userinfo = r.text[word_start..local.start()]
+                .split_once("://")
+                .is_some_and(|(_, authority)| !authority.contains('/'));
+            let (start, end) = match mode {
+                _ if userinfo => (local.start(), local.end()),
+                EmailMode::Full => (local.start(), domain.end()),
+                EmailMode::Local => (local.start(), local.end()),
+                EmailMode::Domain => (domain.start(), domain.end()),
+            };
+            Edit {
+                start,
+                end,
+                label: EMAIL_LABEL.to_string(),
+                // Any echoed segment of an address is personal data
+                structure: r.opaque_structure(&r.text[start..end]),
+            }
+        })
+        .collect();
+    r.apply(edits, FilterKind::Patterns);
+}
+
+// ============================================================================
+// Entropy-based detection
+// ============================================================================
+
+/// Entropy detection configuration (can be overridden via env vars)
+#[derive(Debug, Clone)]
+pub struct EntropyConfig {
+    pub threshold_hex: f64,
+    pub threshold_base64: f64,
+    pub threshold_alphanumeric: f64,
+    pub min_length: usize,
+    pub max_length: usize,
+    /// Check tokens longer than `max_length` on a `max_length`-char prefix
+    /// sample instead of skipping them (the whole token is redacted if high)
+    pub sample_long: bool,
+    /// Custom token alphabets, checked after hex and before the builtin
+    /// charsets, in order
+    pub charsets: Vec<EntropyCharset>,
+    /// How a token over its threshold is confirmed
+    pub model: EntropyModel,
+    /// Keywords that mark a token within 50 chars after them as not secret
+    /// (`excluded:CONTEXT`), matched case-insensitively
+    pub context_keywords: Vec<String>,
+}
+
+/// How the entropy filter scores tokens
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EntropyModel {
+    /// Shannon entropy of the characters alone
+    #[default]
+    Shannon,
+    /// Shannon entropy, then tokens whose letter pairs are typical of English
+    /// (long hyphenated slugs, identifiers) are left alone
+    Bigram,
+}
+
+/// A named token alphabet with its own entropy threshold, for custom token
+/// encodings (Crockford base32, z-base-32) that would otherwise get the
+/// alphanumeric threshold
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntropyCharset {
+    /// Shown in markers in place of the builtin charset (`crock32:26:4.4`);
+    /// must differ from the builtin names and their abbreviations
+    pub name: String,
+    /// Every character a token of this charset may contain (case-sensitive)
+    pub alphabet: String,
+    pub threshold: f64,
+}
+
+impl Default for EntropyConfig {
+    fn default() -> Self {
+        Self {
+            threshold_hex: ENTROPY_THRESHOLD_HEX,
+            threshold_base64: ENTROPY_THRESHOLD_BASE64,
+            threshold_alphanumeric: ENTROPY_THRESHOLD_ALPHANUMERIC,
+            min_length: ENTROPY_MIN_LENGTH,
+            max_length: ENTROPY_MAX_LENGTH,
+            sample_long: false,
+            charsets: Vec::new(),
+            model: EntropyModel::Shannon,
+            context_keywords: ENTROPY_CONTEXT_KEYWORDS
+                .iter()
+                .map(|kw| kw.to_string())
+                .collect(),
+        }
+    }
+}
+
+impl EntropyConfig {
+    /// Default config with `SECRETS_FILTER_ENTROPY_*` environment overrides applied
+    pub fn from_env() -> Self {
+        get_entropy_config()
+    }
+}
+
+/// Get entropy config with environment variable overrides
+fn get_entropy_config() -> EntropyConfig {
+    let mut config = EntropyConfig::default();
+
+    // Global threshold override
+    if let Ok(val) = env::var("SECRETS_FILTER_ENTROPY_THRESHOLD")
+        && let Ok(t) = val.parse::<f64>()
+    {
+        config.threshold_hex = t;
+        config.threshold_base64 = t;
+        config.threshold_alphanumeric = t;
+    }
+
+    // Per-charset overrides
+    if let Ok(val) = env::var("SECRETS_FILTER_ENTROPY_HEX")
+        && let Ok(t) = val.parse::<f64>()
+    {
+        config.threshold_hex = t;
+    }
+    if let Ok(val) = env::var("SECRETS_FILTER_ENTROPY_BASE64")
+        && let Ok(t) = val.parse::<f64>()
+    {
+        config.threshold_base64 = t;
+    }
+
+    // Length overrides
+    if let Ok(val) = env::var("SECRETS_FILTER_ENTROPY_MIN_LEN")
+        && let Ok(l) = val.parse::<usize>()
+    {
+        config.min_length = l;
+    }
+    if let Ok(val) = env::var("SECRETS_FILTER_ENTROPY_MAX_LEN")
+        && let Ok(l) = val.parse::<usize>()
+    {
+        config.max_length = l;
+    }
+    if let Ok(val) = env::var("SECRETS_FILTER_ENTROPY_SAMPLE_LONG") {
+        config.sample_long = is_truthy(&val);
+    }
+
+    // Context keywords: `+a,b` adds to the builtin list, `a,b` replaces it
+    if let Ok(val) = env::var("SECRETS_FILTER_ENTROPY_KEYWORDS") {
+        let (extend, list) = match val.strip_prefix('+') {
+            Some(rest) => (true, rest),
+            None => (false, val.as_str()),
+        };
+        if !extend {
+            config.context_keywords.clear();
+        }
+        config.context_keywords.extend(
+            list.split(',')
+                .map(str::trim)
+                .filter(|kw| !kw.is_empty())
+                .map(str::to_string),
+        );
+    }
+
+    config
+}
+
+/// Calculate Shannon entropy of a string in bits
+/// H = -Σ p(x) log₂ p(x)
+fn shannon_entropy(s: &str) -> f64 {
+    if s.is_empty() {
+        return 0.0;
+    }
+
+    let mut counts: HashMap<char, usize> = HashMap::new();
+    for c in s.chars() {
+        *counts.entry(c).or_insert(0) += 1;
+    }
+
+    let length = s.len() as f64;
+    let mut entropy = 0.0;
+    for &count in counts.values() {
+        let p = count as f64 / length;
+        entropy -= p * p.log2();
+    }
+    entropy
+}
+
+/// Character set definitions
+const CHARSET_HEX: &str = "0123456789abcdef";
+/// Base64 alphabet; `=` is only valid as trailing padding (see `split_base64_padding`)
+const CHARSET_BASE64: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const CHARSET_ALPHANUMERIC: &str =
+    "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789_-";
+
+/// Split off genuine base64 padding: one or two trailing `=`
+fn split_base64_padding(s: &str) -> (&str, &str) {
+    let body = s.trim_end_matches('=');
+    match s.len() - body.len() {
+        1 | 2 if !body.is_empty() => (body, &s[body.len()..]),
+        _ => (s, ""),
+    }
+}
+
+/// Classify a string's character set
+/// Returns: "hex", the name of a matching custom charset, "alphanumeric",
+/// "base64", or "mixed"
+///
+/// Trailing padding marks a value as base64; any other `=` makes it mixed.
+fn classify_charset<'a>(s: &str, custom: &'a [EntropyCharset]) -> &'a str {
+    let all_in = |s: &str, charset: &str| s.chars().all(|c| charset.contains(c));
+    let (body, padding) = split_base64_padding(s);
+    if !padding.is_empty() {
+        return if all_in(body, CHARSET_BASE64) {
+            "base64"
+        } else {
+            "mixed"
+        };
+    }
+
+    // Check hex first (most restrictive), either case
+    if s.chars()
+        .all(|c| c.is_ascii() && CHARSET_HEX.contains(c.to_ascii_lowercase()))
+    {
+        return "hex";
+    }
+
+    // Custom alphabets take precedence over the broader builtin ones
+    if let Some(charset) = custom.iter().find(|c| all_in(s, &c.alphabet)) {
+        return &charset.name;
+    }
+
+    // Check alphanumeric (common for tokens)
+    if all_in(s, CHARSET_ALPHANUMERIC) {
+        return "alphanumeric";
+    }
+
+    // Check base64
+    if all_in(s, CHARSET_BASE64) {
+        return "base64";
+    }
+
+    "mixed"
+}
+
+/// Token with its position in the text it was taken from
+struct Token<'a> {
+    text: &'a str,
+    start: usize,
+    end: usize,
+}
+
+/// Extract potential secret tokens from text
+///
+/// Tokens are the runs between delimiter matches, so their positions come
+/// straight from the single delimiter scan and the text is borrowed, not copied.
+fn extract_tokens<'a>(
+    text: &'a str,
+    min_len: usize,
+    max_len: usize,
+    delim_re: &Regex,
+) -> Vec<Token<'a>> {
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    let delims = delim_re
+        .find_iter(text)
+        .map(|m| (m.start(), m.end()))
+        .chain(std::iter::once((text.len(), text.len())));
+
+    for (delim_start, delim_end) in delims {
+        let token_start = start;
+        start = delim_end;
+        if delim_start == token_start {
+            continue;
+        }
+        let mut end = delim_start;
+
+        // `=` is a delimiter, but one or two right before another
+        // delimiter (or the end) are base64 padding, not `key=value`
+        let pad = text[end..delim_end]
+            .bytes()
+            .take_while(|&b| b == b'=')
+            .count();
+        if (1..=2).contains(&pad) && (end + pad < delim_end || delim_end == text.len()) {
+            end += pad;
+        }
+        let part = &text[token_start..end];
+
+        // Filter by length
+        if part.len() < min_len || part.len() > max_len {
+            continue;
+        }
+
+        // Skip if all alphabetic (variable names)
+        if part.chars().all(|c| c.is_ascii_alphabetic()) {
+            continue;
+        }
+
+        // Skip if all numeric (IDs, line numbers)
+        if part.chars().all(|c| c.is_ascii_digit()) {
+            continue;
+        }
+
+        tokens.push(Token {
+            text: part,
+            start: token_start,
+            end,
+        });
+    }
+
+    tokens
+}
+
+/// Check if a position in text is preceded by a context keyword (within 50 chars)
+fn has_context_keyword(text: &str, pos: usize, keywords: &[impl AsRef<str>]) -> bool {
+    if keywords.is_empty() {
+        return false;
+    }
+
+    let start = pos.saturating_sub(50);
+    let prefix = text[start..pos].to_lowercase();
+
+    for kw in keywords {
+        if prefix.contains(&kw.as_ref().to_lowercase()) {
+            return true;
+        }
+    }
+
+    false
+}
+
+/// Check if token matches an exclusion pattern
+/// Returns: Some(label) if excluded, None otherwise
+fn matches_exclusion(
+    token: &str,
+    text: &str,
+    pos: usize,
+    exclusion_regexes: &[(Regex, &EntropyExclusion)],
+    context_keywords: &[String],
+) -> Option<&'static str> {
+    for (regex, excl) in exclusion_regexes {
+        if regex.is_match(token) {
+            // Check context keywords if present
+            if let Some(context_kw) = excl.context_keywords {
+                if has_context_keyword(text, pos, context_kw) {
+                    return Some(excl.label);
+                }
+                // Has context keywords but none found - not excluded
+                continue;
+            }
+            // No context keywords required - excluded
+            return Some(excl.label);
+        }
+    }
+
+    // Check global context keywords
+    if has_context_keyword(text, pos, context_keywords) {
+        return Some("CONTEXT");
+    }
+
+    None
+}
+
+/// Short charset name used in markers
+fn charset_abbrev(charset: &str) -> &str {
+    match charset {
+        "base64" => "b64",
+        "alphanumeric" => "alnum",
+        "mixed" => "mix",
+        // hex and custom charsets by name
+        other => other,
+    }
+}
+
+/// Create structure description for entropy redaction
+/// Example: hex:40:3.8 (hex:40 without a score)
+fn describe_entropy_structure(
+    token: &str,
+    entropy: f64,
+    charset: &str,
+    precision: Option<usize>,
+) -> String {
+    match precision {
+        Some(p) => format!(
+            "{}:{}:{:.*}",
+            charset_abbrev(charset),
+            token.len(),
+            p,
+            entropy
+        ),
+        None => format!("{}:{}", charset_abbrev(charset), token.len()),
+    }
+}
+
+/// Exclusion entry for user-supplied allowlist patterns (full-token matches)
+static ALLOWLIST_EXCLUSION: EntropyExclusion = EntropyExclusion {
+    pattern: "",
+    label: "ALLOWLIST",
+    case_insensitive: false,
+    context_keywords: None,
+};
+
+/// Build compiled exclusion regexes from patterns
+fn build_exclusion_regexes() -> Vec<(Regex, &'static EntropyExclusion)> {
+    ENTROPY_EXCLUSIONS
+        .iter()
+        .filter_map(|excl| {
+            let regex = if excl.case_insensitive {
+                // ASCII-only folding: the regex crate is built without unicode-case
+                Regex::new(&format!("(?i-u)^{}$", excl.pattern)).ok()
+            } else {
+                Regex::new(&format!("^{}$", excl.pattern)).ok()
+            };
+            regex.map(|r| (r, excl))
+        })
+        .collect()
+}
+
+/// A JSON string literal, and a following `:` if it is an object key
+const JSON_STRING_PATTERN: &str = r#""((?:[^"\\]|\\.)*)"(\s*:)?"#;
+
+/// Byte ranges of the string values (not keys) on a line that looks like
+/// JSON: it starts with `{`, `[` or `"` (a pretty-printed member). None for
+/// other lines.
+fn json_value_spans(text: &str, string_re: &Regex) -> Option<Vec<(usize, usize)>> {
+    if !text.trim_start().starts_with(['{', '[', '"']) {
+        return None;
+    }
+    Some(
+        string_re
+            .captures_iter(text)
+            .filter(|caps| caps.get(2).is_none())
+            .filter_map(|caps| caps.get(1))
+            .map(|m| (m.start(), m.end()))
+            .collect(),
+    )
+}
+
+/// Detect and redact high-entropy strings
+///
+/// With `json_string_re`, tokens on JSON-looking lines are only checked
+/// inside string values, never in keys or between strings.
+fn redact_entropy(
+    r: &mut Redaction,
+    config: &EntropyConfig,
+    exclusion_regexes: &[(Regex, &EntropyExclusion)],
+    token_delim_re: &Regex,
+    json_string_re: Option<&Regex>,
+) {
+    let text = r.text.as_str();
+    let max_len = if config.sample_long {
+        usize::MAX
+    } else {
+        config.max_length
+    };
+    let tokens = extract_tokens(text, config.min_length, max_len, token_delim_re);
+    let json_values = json_string_re.and_then(|re| json_value_spans(text, re));
+
+    let mut edits = Vec::new();
+    for token in &tokens {
+        if let Some(spans) = &json_values
+            && !spans
+                .iter()
+                .any(|&(start, end)| start <= token.start && token.end <= end)
+        {
+            continue;
+        }
+        // Check exclusions
+        let excluded = matches_exclusion(
+            token.text,
+            text,
+            token.start,
+            exclusion_regexes,
+            &config.context_keywords,
+        );
+        if excluded.is_some() && r.skips.is_none() {
+            continue;
+        }
+
+        // Over-long tokens (sample_long) are scored on a prefix sample for cost
+        let sample = if token.text.len() > config.max_length {
+            &token.text[..token.text.floor_char_boundary(config.max_length)]
+        } else {
+            token.text
+        };
+
+        // Classify character set and get threshold
+        let charset = classify_charset(sample, &config.charsets);
+        let threshold = match charset {
+            "hex" => config.threshold_hex,
+            "base64" => config.threshold_base64,
+            "alphanumeric" => config.threshold_alphanumeric,
+            "mixed" => config.threshold_alphanumeric, // mixed uses alphanumeric threshold
+            custom => config
+                .charsets
+                .iter()
+                .find(|c| c.name == custom)
+                .map_or(config.threshold_alphanumeric, |c| c.threshold),
+        };
+
+        // Calculate entropy (padding is structure, not randomness)
+        let entropy = shannon_entropy(split_base64_padding(sample).0);
+
+        // Under the bigram model, word-like tokens are not secrets however
+        // varied their characters
+        let word_like = match config.model {
+            EntropyModel::Bigram if entropy >= threshold => {
+                bigram::english_score(sample).filter(|&score| score >= bigram::WORD_LIKE_SCORE)
+            }
+            _ => None,
+        };
+
+        let reason = match excluded {
+            Some(label) => SkipReason::Excluded(label),
+            None => match word_like {
+                Some(score) => SkipReason::WordLike(score),
+                None if entropy >= threshold => {
+                    edits.push(Edit {
+                        start: token.start,
+                        end: token.end,
+                        label: "HIGH_ENTROPY".to_string(),
+                        structure: r.entropy_structure(token.text, entropy, charset),
+                    });
+                    continue;
+                }
+                None if threshold - entropy <= ENTROPY_NEAR_MISS => {
+                    SkipReason::BelowThreshold(threshold - entropy)
+                }
+                None => continue,
+            },
+        };
+        // Parts of markers left by earlier filters are not candidates
+        if r.only_markers(token.start, token.end) {
+            continue;
+        }
+        let skip = EntropySkip {
+            start: r.orig_start(token.start),
+            end: r.orig_end(token.end),
+            structure: describe_entropy_structure(
+                token.text,
+                entropy,
+                charset,
+                Some(DEFAULT_ENTROPY_PRECISION),
+            ),
+            reason,
+        };
+        if let Some(skips) = &mut r.skips {
+            skips.push(skip);
+        }
+    }
+
+    r.apply(edits, FilterKind::Entropy);
+}
+
+/// Regex for the values of `keys` in JSON, YAML and `key=value` syntax: the
+/// key (optionally quoted), `:` or `=`, then a double-quoted, single-quoted
+/// or bare value in groups 1 to 3
+fn entropy_keys_pattern(keys: &[String]) -> String {
+    let keys: Vec<String> = keys.iter().map(|k| regex::escape(k)).collect();
+    format!(
+        r#"(?:^|[^A-Za-z0-9_-])["']?(?:{})["']?\s*[:=]\s*(?:"([^"\r\n]*)"|'([^'\r\n]*)'|([^\s"',;}}\]#]+))"#,
+        keys.join("|")
+    )
+}
+
+/// Redact the values of the `entropy_keys` as `HIGH_ENTROPY`, whatever
+/// their score: the key says they are secret
+fn redact_entropy_keys(
+    r: &mut Redaction,
+    keys_re: &Regex,
+    charsets: &[EntropyCharset],
+    min_context_len: usize,
+) {
+    let mut edits = Vec::new();
+    for c in keys_re.captures_iter(&r.text) {
+        let Some(value) = c.get(1).or_else(|| c.get(2)).or_else(|| c.get(3)) else {
+            continue;
+        };
+        if value.as_str().chars().count() < min_context_len {
+            continue;
+        }
+        let token = value.as_str();
+        edits.push(Edit {
+            start: value.start(),
+            end: value.end(),
+            label: "HIGH_ENTROPY".to_string(),
+            structure: r.entropy_structure(
+                token,
+                shannon_entropy(token),
+                classify_charset(token, charsets),
+            ),
+        });
+    }
+    r.apply(edits, FilterKind::Entropy);
+}
+
+// ============================================================================
+// Redactor
+// ============================================================================
+
+/// Compiled redaction state: secrets, patterns and entropy settings
+pub struct Redactor {
+    config: FilterConfig,
+    /// Env and registered secret values, None if there are none
+    secrets: Option<SecretValues>,
+    patterns: Vec<Pattern>,
+    context_patterns: Vec<ContextPattern>,
+    special_patterns: SpecialPatterns,
+    /// Form body regexes, None unless form-aware redaction is on
+    form_patterns: Option<FormPatterns>,
+    /// Token regex for reversed-secret detection, None unless enabled
+    obfuscated_token_re: Option<Regex>,
+    /// JWT regex when only signatures are redacted (`JwtMode::SignatureOnly`)
+    jwt_signature_re: Option<Regex>,
+    /// Email regex and mode, None unless email redaction is on
+    email_re: Option<(Regex, EmailMode)>,
+    /// Workflow command regex when their syntax is kept readable
+    workflow_command_re: Option<Regex>,
+    /// Key-at-end and value-at-start regexes for JSON values split over lines
+    json_split_re: Option<(Regex, Regex)>,
+    /// Name and value regexes for Kubernetes env entries
+    k8s_env_re: Option<(Regex, Regex)>,
+    /// Group header and password regexes for MySQL option files
+    mycnf_re: Option<(Regex, Regex)>,
+    /// START and END sentinels of fenced regions (`redact_between`)
+    fence: Option<(String, String)>,
+    /// Strip invisible characters from lines before redaction
+    normalize_unicode: bool,
+    /// Values of these keys are redacted whatever their entropy
+    entropy_keys_re: Option<Regex>,
+    entropy_config: Option<EntropyConfig>,
+    exclusion_regexes: Vec<(Regex, &'static EntropyExclusion)>,
+    token_delim_re: Option<Regex>,
+    /// JSON string regex when entropy only checks JSON values
+    json_string_re: Option<Regex>,
+    /// `NAME=value` line regex, None unless env dump redaction is on
+    env_dump_re: Option<Regex>,
+    only_labels: Option<HashSet<String>>,
+    structure_format: StructureFormat,
+    min_context_len: usize,
+    hash: Option<SecretHash>,
+    fingerprint: Option<Vec<u8>>,
+    preserve_length: bool,
+    merge_adjacent: bool,
+    findings_only: bool,
+    entropy_precision: Option<usize>,
+    allow: Vec<Regex>,
+    marker_prefix: Option<String>,
+    /// Marker names for labels (`--label-map`); findings keep the label
+    label_map: HashMap<String, String>,
+    echo_prefix: bool,
+    /// BEGIN/END markers, None if private key detection is off
+    private_key_markers: Option<(Regex, Regex)>,
+    private_key_structure: PrivateKeyStructure,
+    /// Key type of the block last entered (see `private_key_marker`)
+    private_key_type: String,
+    stream: StreamState,
+    /// Value expected on the next stream line
+    pending: Continuation,
+    /// Inside a client group of a MySQL option file
+    mycnf_client: bool,
+    /// Digests of the values seen so far, when the first occurrence of each
+    /// is kept readable
+    first_seen: Option<SeenValues>,
+    /// Input of [`redact_chunk`](Redactor::redact_chunk) held back until
+    /// its line (or token) is complete
+    chunk_tail: Vec<u8>,
+}
+
+/// Per-stream state of [`Redactor::redact_stream_line`]
+#[derive(Debug, Default)]
+enum StreamState {
+    #[default]
+    Normal,
+    /// Inside a private key block: lines held back until the END marker
+    InPrivateKey(Vec<String>),
+    /// Block exceeded MAX_PRIVATE_KEY_BUFFER: swallow lines until the END marker
+    PrivateKeyOverflow,
+}
+
+/// Result of feeding one line to [`Redactor::redact_stream_line`]
+#[derive(Debug)]
+pub enum StreamOutput {
+    /// The redacted line and its findings
+    Line(String, Vec<Finding>),
+    /// Swallowed by a private key block; nothing to write for now
+    Suppressed,
+    /// A private key block of `lines` input lines ended here (or overflowed):
+    /// write one [`private_key_marker`](Redactor::private_key_marker) line
+    /// in its place
+    PrivateKey { lines: usize },
+}
+
+impl Redactor {
+    /// Start building a redactor (builtin patterns, no secret values)
+    pub fn builder() -> RedactorBuilder {
+        RedactorBuilder::new()
+    }
+
+    /// Whether `label` may be redacted (false if excluded by `only_labels`)
+    pub fn label_enabled(&self, label: &str) -> bool {
+        self.only_labels
+            .as_ref()
+            .is_none_or(|labels| labels.contains(label))
+    }
+
+    /// Name shown in markers for `label`: its `label_map` entry, if any
+    pub fn marker_label<'a>(&'a self, label: &'a str) -> &'a str {
+        self.label_map.get(label).map_or(label, String::as_str)
+    }
+
+    /// Whether an active filter can report `label`: a pattern, entropy, the
+    /// JSON continuation, a secret's name or a sensitive env dump name
+    pub fn knows_label(&self, label: &str) -> bool {
+        self.pattern_labels().contains(&label)
+            || (label == "HIGH_ENTROPY" && (self.config.entropy || self.entropy_keys_re.is_some()))
+            || (label == JSON_SECRET_LABEL && self.json_split_re.is_some())
+            || (label == K8S_ENV_SECRET_LABEL && self.k8s_env_re.is_some())
+            || (label == MYCNF_PASSWORD_LABEL && self.mycnf_re.is_some())
+            || self.secrets.as_ref().is_some_and(|s| {
+                s.names
+                    .iter()
+                    .chain(s.names_by_value.values())
+                    .any(|name| name == label)
+            })
+            || (self.env_dump_re.is_some() && is_sensitive_env_name(label))
+    }
+
+    /// Labels of all active patterns (builtin and custom), sorted, for
+    /// reporting which ones never matched
+    pub fn pattern_labels(&self) -> Vec<&str> {
+        let mut labels: Vec<&str> = self
+            .patterns
+            .iter()
+            .map(|p| p.label.as_str())
+            .chain(self.context_patterns.iter().map(|p| p.label.as_str()))
+            .collect();
+        let specials = [
+            (
+                &self.special_patterns.git_credential,
+                &GIT_CREDENTIAL_PATTERN,
+            ),
+            (&self.special_patterns.docker_auth, &DOCKER_AUTH_PATTERN),
+        ];
+        for (re, sp) in specials {
+            if re.is_some() {
+                labels.push(sp.label);
+            }
+        }
+        if self.form_patterns.is_some() {
+            labels.push(FORM_SECRET_LABEL);
+        }
+        if self.email_re.is_some() {
+            labels.push(EMAIL_LABEL);
+        }
+        if self.private_key_markers.is_some() {
+            labels.push("PRIVATE_KEY");
+        }
+        labels.sort_unstable();
+        labels.dedup();
+        labels
+    }
+
+    /// Feed the next line of a stream (including its newline, if any)
+    ///
+    /// Unlike [`redact_line`](Self::redact_line) this recognises multiline
+    /// private key blocks, which are replaced as a whole and fail closed: a
+    /// block that never ends is still reported by
+    /// [`finish_stream`](Self::finish_stream).
+    ///
+    /// A block runs from a BEGIN line to the first END line. A BEGIN inside a
+    /// block is content of that block (a truncated key followed by a complete
+    /// one is replaced as one block), and a line with BEGIN and a later END
+    /// is a block by itself. Lines after END are filtered normally.
+    pub fn redact_stream_line(&mut self, line: &str) -> StreamOutput {
+        let Some((begin, end)) = &self.private_key_markers else {
+            return self.redact_stream_text(line);
+        };
+
+        match &mut self.stream {
+            StreamState::Normal => match begin.find(line) {
+                Some(m) => {
+                    self.private_key_type = private_key_type(m.as_str());
+                    if end.is_match(&line[m.end()..]) {
+                        StreamOutput::PrivateKey { lines: 1 }
+                    } else {
+                        self.stream = StreamState::InPrivateKey(vec![line.to_string()]);
+                        StreamOutput::Suppressed
+                    }
+                }
+                None => self.redact_stream_text(line),
+            },
+            StreamState::InPrivateKey(buffer) => {
+                buffer.push(line.to_string());
+                let lines = buffer.len();
+                if end.is_match(line) {
+                    self.stream = StreamState::Normal;
+                    StreamOutput::PrivateKey { lines }
+                } else if lines > MAX_PRIVATE_KEY_BUFFER {
+                    // Buffer overflow - redact entirely (fail closed, don't leak)
+                    self.stream = StreamState::PrivateKeyOverflow;
+                    StreamOutput::PrivateKey { lines }
+                } else {
+                    StreamOutput::Suppressed
+                }
+            }
+            StreamState::PrivateKeyOverflow => {
+                // Already reported; wait for the END marker
+                if end.is_match(line) {
+                    self.stream = StreamState::Normal;
+                }
+                StreamOutput::Suppressed
+            }
+        }
+    }
+
+    /// Structure of the marker for a private key block of `lines` lines,
+    /// the one last reported by [`redact_stream_line`](Self::redact_stream_line)
+    /// or [`finish_stream`](Self::finish_stream)
+    pub fn private_key_structure(&self, lines: usize) -> String {
+        let PrivateKeyStructure {
+            key_type,
+            lines: show_lines,
+        } = self.private_key_structure;
+        if !key_type && !show_lines {
+            return "multiline".to_string();
+        }
+        let mut parts = Vec::new();
+        match self.structure_format {
+            StructureFormat::Compact => {
+                if key_type {
+                    parts.push(self.private_key_type.clone());
+                }
+                if show_lines {
+                    parts.push(format!("{}lines", lines));
+                }
+                parts.join(":")
+            }
+            StructureFormat::Verbose => {
+                if key_type {
+                    parts.push(format!("type={}", self.private_key_type));
+                }
+                if show_lines {
+                    parts.push(format!("lines={}", lines));
+                }
+                parts.join(" ")
+            }
+        }
+    }
+
+    /// Marker replacing a private key block of `lines` lines, formatted like
+    /// other markers (marker prefix, label map, structure format)
+    pub fn private_key_marker(&self, lines: usize) -> String {
+        let label = self.marker_label("PRIVATE_KEY");
+        let structure = self.private_key_structure(lines);
+        match &self.marker_prefix {
+            Some(prefix) => format!("[REDACTED:{}:{}:{}]", prefix, label, structure),
+            None => format!("[REDACTED:{}:{}]", label, structure),
+        }
+    }
+
+    /// End of stream: reports an unterminated private key block, then resets
+    pub fn finish_stream(&mut self) -> Option<StreamOutput> {
+        let out = match &self.stream {
+            StreamState::InPrivateKey(buffer) => Some(StreamOutput::PrivateKey {
+                lines: buffer.len(),
+            }),
+            _ => None,
+        };
+        self.reset();
+        out
+    }
+
+    /// Feed the next chunk of a stream read in arbitrary pieces (fixed-size
+    /// `read()` calls) and get back the redacted bytes that are final so far
+    ///
+    /// Complete lines are redacted as by
+    /// [`redact_stream_line`](Self::redact_stream_line); the rest is held, so
+    /// a secret split across two chunks is still found. A line growing past
+    /// [`MAX_CHUNK_TAIL`] bytes without a newline (one huge JSON document) is
+    /// redacted up to its last whitespace, `,` or `;`, and the token after it
+    /// is held: only a context pattern spanning that cut can be missed.
+    /// [`finish_chunks`](Self::finish_chunks) flushes the held bytes.
+    ///
+    /// Input is decoded as UTF-8; invalid sequences become U+FFFD.
+    pub fn redact_chunk(&mut self, chunk: &[u8]) -> Vec<u8> {
+        let mut tail = std::mem::take(&mut self.chunk_tail);
+        tail.extend_from_slice(chunk);
+        let mut out = Vec::new();
+        let mut start = 0;
+        while let Some(nl) = tail[start..].iter().position(|&b| b == b'\n') {
+            let end = start + nl + 1;
+            self.redact_chunk_piece(&tail[start..end], &mut out);
+            start = end;
+        }
+        if tail.len() - start > MAX_CHUNK_TAIL
+            && let Some(cut) = tail[start..]
+                .iter()
+                .rposition(|b| b.is_ascii_whitespace() || matches!(b, b',' | b';'))
+        {
+            let end = start + cut + 1;
+            self.redact_chunk_piece(&tail[start..end], &mut out);
+            start = end;
+        }
+        tail.drain(..start);
+        self.chunk_tail = tail;
+        out
+    }
+
+    /// End of a chunked stream: redact the held bytes and report an
+    /// unterminated private key block, then reset
+    pub fn finish_chunks(&mut self) -> Vec<u8> {
+        let tail = std::mem::take(&mut self.chunk_tail);
+        let mut out = Vec::new();
+        if !tail.is_empty() {
+            self.redact_chunk_piece(&tail, &mut out);
+        }
+        if let Some(StreamOutput::PrivateKey { lines }) = self.finish_stream() {
+            out.extend_from_slice(self.private_key_marker(lines).as_bytes());
+        }
+        out
+    }
+
+    fn redact_chunk_piece(&mut self, piece: &[u8], out: &mut Vec<u8>) {
+        let piece = String::from_utf8_lossy(piece);
+        match self.redact_stream_line(&piece) {
+            StreamOutput::Line(text, _) => out.extend_from_slice(text.as_bytes()),
+            StreamOutput::Suppressed => {}
+            StreamOutput::PrivateKey { lines } => {
+                out.extend_from_slice(self.private_key_marker(lines).as_bytes());
+                out.extend_from_slice(internal::line_ending(&piece).as_bytes());
+            }
+        }
+    }
+
+    /// Hand back lines held for a possible private key block and reset, so
+    /// the caller can redact them line by line (e.g. before binary passthrough)
+    pub fn take_held_lines(&mut self) -> Vec<String> {
+        match std::mem::take(&mut self.stream) {
+            StreamState::InPrivateKey(buffer) => buffer,
+            _ => Vec::new(),
+        }
+    }
+
+    /// Whether the next stream line depends on earlier ones: it is inside a
+    /// private key block, a fenced region or a MySQL client group, or may
+    /// hold the value of a JSON key or Kubernetes env name on the previous
+    /// line
+    pub fn in_progress(&self) -> bool {
+        !matches!(self.stream, StreamState::Normal)
+            || self.pending != Continuation::None
+            || self.mycnf_client
+    }
+
+    /// Whether `line` may open per-stream state: a private key block, a
+    /// fence, a MySQL option group, or a JSON key or Kubernetes env name
+    /// whose value follows on the next line (also true whenever first
+    /// occurrences are tracked). Otherwise, with nothing
+    /// [in progress](Self::in_progress),
+    /// [`redact_stream_line`](Self::redact_stream_line) redacts the line like
+    /// [`redact_line_with_findings`](Self::redact_line_with_findings) and
+    /// leaves no state behind, so such lines may be redacted out of order.
+    pub fn carries_stream_state(&self, line: &str) -> bool {
+        self.first_seen.is_some()
+            || self
+                .private_key_markers
+                .as_ref()
+                .is_some_and(|(begin, _)| begin.is_match(line))
+            || self
+                .fence
+                .as_ref()
+                .is_some_and(|(start, _)| line.contains(start.as_str()))
+            || self
+                .mycnf_re
+                .as_ref()
+                .is_some_and(|(group_re, _)| group_re.is_match(line))
+            || self
+                .json_split_re
+                .as_ref()
+                .is_some_and(|(key_re, _)| key_re.is_match(line))
+            || self
+                .k8s_env_re
+                .as_ref()
+                .is_some_and(|(name_re, _)| name_re.is_match(line))
+    }
+
+    /// Distinct values currently remembered by
+    /// [`keep_first_occurrence`](RedactorBuilder::keep_first_occurrence)
+    pub fn first_occurrences_tracked(&self) -> usize {
+        self.first_seen
+            .as_ref()
+            .map_or(0, |seen| seen.last_seen.len())
+    }
+
+    /// Forget the per-stream state (an open private key block, a pending
+    /// JSON or env value, the option file group, a chunk tail) so the next
+    /// line starts a new document; compiled patterns and secrets are kept.
+    ///
+    /// The values remembered by
+    /// [`keep_first_occurrence`](RedactorBuilder::keep_first_occurrence) are
+    /// kept too: a value already shown once stays redacted in later
+    /// documents, so resetting never makes a secret readable again.
+    pub fn reset(&mut self) {
+        self.stream = StreamState::Normal;
+        self.pending = Continuation::None;
+        self.mycnf_client = false;
+        self.chunk_tail.clear();
+    }
+
+    /// Redact a stream line outside private key blocks, carrying a sensitive
+    /// JSON key at the end of a line or a sensitive Kubernetes env name over
+    /// to the value on the next one; tracks the MySQL option file group
+    fn redact_stream_text(&mut self, line: &str) -> StreamOutput {
+        if let Some((group_re, _)) = &self.mycnf_re
+            && let Some(c) = group_re.captures(line)
+        {
+            let group = c[1].trim().to_ascii_lowercase();
+            self.mycnf_client = MYCNF_CLIENT_GROUPS.contains(&group.as_str());
+        }
+        let pending = std::mem::replace(&mut self.pending, Continuation::None);
+        let mut first_seen = self.first_seen.take();
+        let (text, findings) = self.redact(line, pending, self.mycnf_client, first_seen.as_mut());
+        self.first_seen = first_seen;
+        if self.fence.as_ref().is_some_and(|(start, end)| {
+            fenced_spans(line, start, end, pending == Continuation::Fenced).1
+        }) {
+            self.pending = Continuation::Fenced;
+        } else if self
+            .json_split_re
+            .as_ref()
+            .is_some_and(|(key_re, _)| key_re.is_match(line))
+        {
+            self.pending = Continuation::JsonValue;
+        } else if self.k8s_env_re.as_ref().is_some_and(|(name_re, _)| {
+            name_re
+                .captures(line)
+                .is_some_and(|c| is_sensitive_k8s_env_name(&c[1]))
+        }) {
+            self.pending = Continuation::K8sEnvValue;
+        } else if line.trim().is_empty() {
+            // Blank lines between key and value keep the key pending
+            self.pending = pending;
+        }
+        StreamOutput::Line(text, findings)
+    }
+
+    /// Redact one line of text with all enabled filters
+    pub fn redact_line(&self, line: &str) -> String {
+        self.redact_line_with_findings(line).0
+    }
+
+    /// Redact one line and report what was redacted, ordered by position
+    ///
+    /// Finding offsets are byte offsets into `line`, or, with
+    /// [`normalize_unicode`](RedactorBuilder::normalize_unicode), into `line`
+    /// with its invisible characters stripped (see [`strip_invisible`]). A
+    /// span redacted by an earlier filter is never re-reported by a later one.
+    pub fn redact_line_with_findings(&self, line: &str) -> (String, Vec<Finding>) {
+        self.redact(line, Continuation::None, false, None)
+    }
+
+    /// Redact `line` in place, with the same result as
+    /// [`Redactor::redact_line`]
+    ///
+    /// The redacted text is written back into `line`'s buffer, which only
+    /// grows when markers make the line longer, so a loop reusing one
+    /// `String` per line allocates no new line buffers once warmed up. The
+    /// filters' own working memory (a scratch copy per redacted line,
+    /// findings, marker text) is allocated as for `redact_line`.
+    pub fn redact_line_in_place(&self, line: &mut String) {
+        thread_local! {
+            /// Copy of the line being redacted, reused across calls
+            static ORIGINAL: std::cell::RefCell<String> = const { std::cell::RefCell::new(String::new()) };
+        }
+        ORIGINAL.with_borrow_mut(|orig| {
+            orig.clear();
+            orig.push_str(line);
+            let buf = std::mem::take(line);
+            *line = self
+                .redact_buffer(orig, buf, Continuation::None, false, None)
+                .0;
+        });
+    }
+
+    /// Run all enabled filters; `pending` marks a line that may open with the
+    /// value of a sensitive key or name on the previous line, `mycnf_client`
+    /// a line in a MySQL client group, `first_seen` the stream's values so
+    /// far when first occurrences are kept
+    fn redact(
+        &self,
+        line: &str,
+        pending: Continuation,
+        mycnf_client: bool,
+        first_seen: Option<&mut SeenValues>,
+    ) -> (String, Vec<Finding>) {
+        self.redact_buffer(line, String::new(), pending, mycnf_client, first_seen)
+    }
+
+    /// `redact`, building the redacted text in `buf`'s allocation
+    fn redact_buffer(
+        &self,
+        line: &str,
+        buf: String,
+        pending: Continuation,
+        mycnf_client: bool,
+        first_seen: Option<&mut SeenValues>,
+    ) -> (String, Vec<Finding>) {
+        let stripped = self
+            .normalize_unicode
+            .then(|| strip_invisible(line))
+            .flatten();
+        let line = stripped.as_deref().unwrap_or(line);
+        let mut r = Redaction::with_buffer(line, buf, self.marker_style(), &self.allow);
+        r.first_seen = first_seen;
+        self.run_filters(&mut r, pending, mycnf_client);
+        if self.merge_adjacent && !self.preserve_length {
+            r.merge_adjacent();
+        }
+        r.findings.sort_by_key(|f| (f.start, f.end));
+        (r.text, r.findings)
+    }
+
+    /// High-entropy candidates on `line` that the entropy filter left alone:
+    /// excluded ones (UUIDs, hashes after a context keyword, the allowlist),
+    /// near misses up to half a bit below their threshold, and word-like
+    /// tokens under [`EntropyModel::Bigram`]
+    ///
+    /// Spans redacted by the other filters are not candidates. Empty unless
+    /// the entropy filter is enabled.
+    pub fn entropy_skips(&self, line: &str) -> Vec<EntropySkip> {
+        let mut r = Redaction::new(line, self.marker_style(), &self.allow);
+        r.skips = Some(Vec::new());
+        self.run_filters(&mut r, Continuation::None, false);
+        r.skips.unwrap_or_default()
+    }
+
+    /// All enabled filters, in order
+    fn run_filters(&self, r: &mut Redaction, pending: Continuation, mycnf_client: bool) {
+        if let Some(fence) = &self.fence {
+            redact_fenced(r, fence, pending == Continuation::Fenced);
+        }
+        if let Some(command_re) = &self.workflow_command_re {
+            keep_workflow_command(r, command_re);
+        }
+        if self.config.values
+            && let Some(secrets) = &self.secrets
+        {
+            redact_env_values(r, secrets);
+        }
+        if self.config.values
+            && let Some(line_re) = &self.env_dump_re
+        {
+            redact_env_dump(
+                r,
+                line_re,
+                |label| self.label_enabled(label),
+                self.min_context_len,
+            );
+        }
+        let continuation = match pending {
+            Continuation::None | Continuation::Fenced => None,
+            Continuation::JsonValue => self
+                .json_split_re
+                .as_ref()
+                .map(|(_, value_re)| (value_re, JSON_SECRET_LABEL)),
+            Continuation::K8sEnvValue => self
+                .k8s_env_re
+                .as_ref()
+                .map(|(_, value_re)| (value_re, K8S_ENV_SECRET_LABEL)),
+        };
+        if let Some((value_re, label)) = continuation {
+            redact_continuation(r, value_re, label, self.min_context_len);
+        }
+        if mycnf_client && let Some((_, password_re)) = &self.mycnf_re {
+            redact_continuation(r, password_re, MYCNF_PASSWORD_LABEL, self.min_context_len);
+        }
+        self.redact_patterns_and_entropy(r);
+    }
+
+    /// Whether an isolated token (no surrounding line) would be redacted, and
+    /// why: the first finding from the patterns, then the entropy filter,
+    /// as configured. Env values are not consulted, and context patterns
+    /// can't match without their prefix, so only token shapes count.
+    pub fn classify(&self, token: &str) -> Option<Finding> {
+        let mut r = Redaction::new(token, self.marker_style(), &self.allow);
+        self.redact_patterns_and_entropy(&mut r);
+        r.findings.into_iter().min_by_key(|f| (f.start, f.end))
+    }
+
+    fn marker_style(&self) -> MarkerStyle<'_> {
+        MarkerStyle {
+            format: self.structure_format,
+            hash: self.hash.as_ref(),
+            fingerprint: self.fingerprint.as_deref(),
+            preserve_length: self.preserve_length,
+            describe: !self.findings_only,
+            entropy_precision: self.entropy_precision,
+            prefix: self.marker_prefix.as_deref(),
+            label_map: &self.label_map,
+            echo_prefix: self.echo_prefix,
+        }
+    }
+
+    /// The patterns and entropy filters, if enabled
+    fn redact_patterns_and_entropy(&self, r: &mut Redaction) {
+        if let Some(jwt_re) = &self.jwt_signature_re {
+            redact_jwt_signatures(r, jwt_re);
+        }
+        if self.config.patterns {
+            redact_patterns(
+                r,
+                &self.patterns,
+                &self.context_patterns,
+                &self.special_patterns,
+                self.form_patterns.as_ref(),
+                self.obfuscated_token_re.as_ref(),
+                self.min_context_len,
+            );
+        }
+        if let Some((email_re, mode)) = &self.email_re {
+            redact_emails(r, email_re, *mode);
+        }
+        if let Some(keys_re) = &self.entropy_keys_re {
+            let charsets = self
+                .entropy_config
+                .as_ref()
+                .map_or(&[][..], |ec| &ec.charsets);
+            redact_entropy_keys(r, keys_re, charsets, self.min_context_len);
+        }
+        if self.config.entropy
+            && let Some(ec) = &self.entropy_config
+            && let Some(delim) = &self.token_delim_re
+        {
+            redact_entropy(
+                r,
+                ec,
+                &self.exclusion_regexes,
+                delim,
+                self.json_string_re.as_ref(),
+            );
+        }
+    }
+}
+
+/// Key type named by a BEGIN marker: the words before `PRIVATE KEY`
+/// (`RSA`, `ENCRYPTED`, `OPENSSH`), `PKCS8` if there are none
+fn private_key_type(begin: &str) -> String {
+    let words = begin
+        .trim_matches('-')
+        .trim_start_matches("BEGIN")
+        .trim_end_matches("PRIVATE KEY")
+        .split_whitespace()
+        .collect::<Vec<_>>();
+    if words.is_empty() {
+        "PKCS8".to_string()
+    } else {
+        words.join("_")
+    }
+}
+
+/// Whether capture group 1 of `pattern` contains an unbounded repeat of `.`
+///
+/// The prefix group stands in for lookbehind, so `.*`, `.+` or `.{n,}` there
+/// lets a context pattern swallow (and keep) arbitrarily long spans.
+fn prefix_has_unbounded_dot(pattern: &str) -> bool {
+    let bytes = pattern.as_bytes();
+    let mut captures = 0;
+    let mut depth = 0;
+    let mut prefix_depth = None;
+    let mut in_class = false;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 1,
+            b'[' if !in_class => in_class = true,
+            b']' if in_class => in_class = false,
+            _ if in_class => {}
+            b'(' => {
+                depth += 1;
+                let rest = &pattern[i + 1..];
+                let capturing = !rest.starts_with('?')
+                    || rest.starts_with("?P<")
+                    || (rest.starts_with("?<")
+                        && !rest.starts_with("?<=")
+                        && !rest.starts_with("?<!"));
+                if capturing {
+                    captures += 1;
+                    if captures == 1 {
+                        prefix_depth = Some(depth);
+                    }
+                }
+            }
+            b')' => {
+                if prefix_depth == Some(depth) {
+                    return false;
+                }
+                depth -= 1;
+            }
+            b'.' if prefix_depth.is_some() => {
+                let rest = &pattern[i + 1..];
+                let unbounded_brace = rest.strip_prefix('{').is_some_and(|r| {
+                    r.split_once('}').is_some_and(|(q, _)| {
+                        q.ends_with(',') && q[..q.len() - 1].bytes().all(|b| b.is_ascii_digit())
+                    })
+                });
+                if rest.starts_with('*') || rest.starts_with('+') || unbounded_brace {
+                    return true;
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    false
+}
+
+/// Builder for [`Redactor`]
+///
+/// Patterns registered with [`add_pattern`](Self::add_pattern) and
+/// [`add_context_pattern`](Self::add_context_pattern) run after the builtin
+/// ones, as part of the patterns filter. A builder can be cloned to build
+/// variants of one configuration.
+#[derive(Clone)]
+pub struct RedactorBuilder {
+    config: FilterConfig,
+    secrets: HashMap<String, String>,
+    entropy_config: Option<EntropyConfig>,
+    extra_patterns: Vec<Pattern>,
+    extra_context_patterns: Vec<ContextPattern>,
+    entropy_allow: Vec<Regex>,
+    only_labels: Option<HashSet<String>>,
+    structure_format: StructureFormat,
+    min_context_len: usize,
+    hash: Option<SecretHash>,
+    fingerprint: Option<Vec<u8>>,
+    preserve_length: bool,
+    findings_only: bool,
+    form_aware: bool,
+    entropy_precision: Option<usize>,
+    allow: Vec<Regex>,
+    detect_obfuscated: bool,
+    jwt_mode: JwtMode,
+    email_mode: Option<EmailMode>,
+    workflow_commands: bool,
+    merge_adjacent: bool,
+    keep_first_occurrence: bool,
+    first_occurrence_cap: usize,
+    marker_prefix: Option<String>,
+    label_map: HashMap<String, String>,
+    echo_prefix: bool,
+    private_key_structure: PrivateKeyStructure,
+    whole_word_secrets: bool,
+    entropy_json_values_only: bool,
+    env_dump: bool,
+    fence: Option<(String, String)>,
+    entropy_keys: Vec<String>,
+    normalize_unicode: bool,
+    private_keys_only: bool,
+}
+
+impl Default for RedactorBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RedactorBuilder {
+    pub fn new() -> Self {
+        Self {
+            config: FilterConfig::default(),
+            secrets: HashMap::new(),
+            entropy_config: None,
+            extra_patterns: Vec::new(),
+            extra_context_patterns: Vec::new(),
+            entropy_allow: Vec::new(),
+            only_labels: None,
+            structure_format: StructureFormat::default(),
+            min_context_len: DEFAULT_MIN_CONTEXT_LEN,
+            hash: None,
+            fingerprint: None,
+            preserve_length: false,
+            findings_only: false,
+            form_aware: false,
+            entropy_precision: Some(DEFAULT_ENTROPY_PRECISION),
+            allow: Vec::new(),
+            detect_obfuscated: false,
+            jwt_mode: JwtMode::default(),
+            email_mode: None,
+            workflow_commands: false,
+            merge_adjacent: false,
+            keep_first_occurrence: false,
+            first_occurrence_cap: DEFAULT_FIRST_OCCURRENCE_CAP,
+            marker_prefix: None,
+            label_map: HashMap::new(),
+            echo_prefix: true,
+            private_key_structure: PrivateKeyStructure::default(),
+            whole_word_secrets: false,
+            entropy_json_values_only: false,
+            env_dump: false,
+            fence: None,
+            entropy_keys: Vec::new(),
+            normalize_unicode: false,
+            private_keys_only: false,
+        }
+    }
+
+    /// Select which filters are active (default: values + patterns)
+    pub fn filters(mut self, config: FilterConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Secret values to redact, keyed by the label used in the marker
+    pub fn secrets(mut self, secrets: HashMap<String, String>) -> Self {
+        self.secrets = secrets;
+        self
+    }
+
+    /// Load secret values from the process environment (see `patterns/env.yaml`)
+    pub fn secrets_from_env(mut self) -> Self {
+        self.secrets = load_secrets();
+        self
+    }
+
+    /// Redact secret values only as whole words, not inside longer words
+    /// (`admin_pass` is left alone in `admin_password`). The values are
+    /// compiled into a single regex when the redactor is built.
+    pub fn whole_word_secrets(mut self, on: bool) -> Self {
+        self.whole_word_secrets = on;
+        self
+    }
+
+    /// Treat `NAME=value` lines (`env`/`printenv` output) as env assignments
+    /// and redact the value when NAME is a secret name, whether or not this
+    /// process has the variable. Part of the values filter.
+    pub fn env_dump(mut self, on: bool) -> Self {
+        self.env_dump = on;
+        self
+    }
+
+    /// Strip zero-width and other invisible format characters (U+200B,
+    /// U+200D, U+FEFF, bidi controls, ...) from each line before redaction,
+    /// so a token split by them still matches. The output and finding
+    /// offsets are then those of the stripped line: the characters are gone
+    /// from the whole line, not only from redacted spans.
+    pub fn normalize_unicode(mut self, on: bool) -> Self {
+        self.normalize_unicode = on;
+        self
+    }
+
+    /// Redact nothing but private key blocks: the values, patterns and
+    /// entropy filters and every other label are off, and lines outside a
+    /// block pass through as they are. Blocks are only found in a stream
+    /// ([`Redactor::redact_stream_line`]).
+    pub fn private_keys_only(mut self, on: bool) -> Self {
+        self.private_keys_only = on;
+        self
+    }
+
+    /// Redact the values of these JSON/YAML/`key=value` keys (exact,
+    /// case-sensitive names such as `clientSecret`) as `HIGH_ENTROPY`,
+    /// however low their entropy, even with the entropy filter off; other
+    /// high-entropy strings are left to that filter
+    pub fn entropy_keys(mut self, keys: &[&str]) -> Self {
+        self.entropy_keys = keys
+            .iter()
+            .filter(|k| !k.is_empty())
+            .map(|k| k.to_string())
+            .collect();
+        self
+    }
+
+    /// Redact everything between the literal sentinels `start` and `end`
+    /// (kept) as `FENCED`, whatever it looks like, independent of the
+    /// filters. In a stream a fence may span lines; a fence that does not
+    /// end runs to the end of its line, or of the stream.
+    pub fn redact_between(mut self, start: &str, end: &str) -> Self {
+        self.fence =
+            (!start.is_empty() && !end.is_empty()).then(|| (start.to_string(), end.to_string()));
+        self
+    }
+
+    /// Add one secret value on top of those already set (replaces an existing name)
+    pub fn add_secret(mut self, name: &str, value: &str) -> Self {
+        if !value.is_empty() {
+            self.secrets.insert(name.to_string(), value.to_string());
+        }
+        self
+    }
+
+    /// How marker structures are rendered (default: compact)
+    pub fn structure_format(mut self, format: StructureFormat) -> Self {
+        self.structure_format = format;
+        self
+    }
+
+    /// What private key block markers show (default: `multiline`)
+    pub fn private_key_structure(mut self, structure: PrivateKeyStructure) -> Self {
+        self.private_key_structure = structure;
+        self
+    }
+
+    /// Replace marker structures with a truncated hash of the secret
+    pub fn hash_secrets(mut self, hash: SecretHash) -> Self {
+        self.hash = Some(hash);
+        self
+    }
+
+    /// Append `:fp=` and a truncated HMAC-SHA-256 of the secret keyed with
+    /// `key` to every structure, for matching against a secret inventory
+    pub fn fingerprint(mut self, key: Vec<u8>) -> Self {
+        self.fingerprint = Some(key);
+        self
+    }
+
+    /// Pad or shorten each marker to the width of the text it replaces, for
+    /// fixed-width logs (see `--preserve-length`)
+    pub fn preserve_length(mut self, on: bool) -> Self {
+        self.preserve_length = on;
+        self
+    }
+
+    /// Show `prefix` before the label in every marker, e.g.
+    /// `[REDACTED:checkout-svc:GITHUB_PAT:ghp_36X]`; findings keep the bare label
+    pub fn marker_prefix(mut self, prefix: &str) -> Self {
+        self.marker_prefix = Some(prefix.to_string());
+        self
+    }
+
+    /// Show `name` instead of `label` in markers, e.g. `vcs.github.pat` for
+    /// `GITHUB_PAT`; findings and `only_labels` keep using the label
+    pub fn label_map(mut self, label: &str, name: &str) -> Self {
+        self.label_map.insert(label.to_string(), name.to_string());
+        self
+    }
+
+    /// Show a token's leading segment (`ghp`, `xoxb`) literally in compact
+    /// structures (default); off classifies it like the rest (`3A_36X`), for
+    /// values whose first segment may itself be sensitive
+    pub fn echo_prefix(mut self, on: bool) -> Self {
+        self.echo_prefix = on;
+        self
+    }
+
+    /// Decimals of the entropy score in `HIGH_ENTROPY` markers (default 1);
+    /// None leaves the score out
+    pub fn entropy_precision(mut self, precision: Option<usize>) -> Self {
+        self.entropy_precision = precision;
+        self
+    }
+
+    /// Also check long tokens reversed against the direct patterns, and
+    /// redact the original when the reversed form matches (off by default:
+    /// costs a pass over every long token)
+    pub fn detect_obfuscated(mut self, on: bool) -> Self {
+        self.detect_obfuscated = on;
+        self
+    }
+
+    /// How much of a JWT to redact (default: the whole token)
+    pub fn jwt_mode(mut self, mode: JwtMode) -> Self {
+        self.jwt_mode = mode;
+        self
+    }
+
+    /// Also redact email addresses as `EMAIL`, whole or only the part `mode`
+    /// names (off by default: addresses are personal data, not secrets)
+    pub fn redact_emails(mut self, mode: EmailMode) -> Self {
+        self.email_mode = Some(mode);
+        self
+    }
+
+    /// Never redact the syntax of GitHub Actions workflow command lines
+    /// (`::add-mask::`, `::error file=...::`): the command name and the `::`
+    /// before the message. Parameters and messages are filtered as usual.
+    pub fn keep_workflow_commands(mut self, on: bool) -> Self {
+        self.workflow_commands = on;
+        self
+    }
+
+    /// Collapse back-to-back markers of one label, separated only by a short
+    /// delimiter (`,`, `;`, `|`, blanks), into `[REDACTED:LABEL:xN]`.
+    /// Findings still count each secret; no effect with `preserve_length`.
+    pub fn merge_adjacent(mut self, on: bool) -> Self {
+        self.merge_adjacent = on;
+        self
+    }
+
+    /// UNSAFE, for tracing where a secret first leaked: in a stream, leave the
+    /// first occurrence of each distinct secret value readable and redact
+    /// only the later ones. Values are remembered (as SHA-256 digests) for
+    /// the redactor's lifetime: [`Redactor::reset`] keeps them, so a value
+    /// is shown once per redactor, not once per document. Single-line calls
+    /// such as [`Redactor::redact_line`] are not affected.
+    pub fn keep_first_occurrence(mut self, on: bool) -> Self {
+        self.keep_first_occurrence = on;
+        self
+    }
+
+    /// Most distinct values [`keep_first_occurrence`](Self::keep_first_occurrence)
+    /// remembers (default [`DEFAULT_FIRST_OCCURRENCE_CAP`], at least 1). Beyond
+    /// it the least recently seen value is forgotten, so memory stays bounded
+    /// on long streams; a forgotten value counts as new, and its next
+    /// occurrence is left readable again. Repeats of recently seen values are
+    /// always redacted.
+    pub fn first_occurrence_cap(mut self, cap: usize) -> Self {
+        self.first_occurrence_cap = cap;
+        self
+    }
+
+    /// Parse `key=value&...` runs (form bodies, query strings) and redact the
+    /// values of sensitive keys only, as `FORM_SECRET` (see `--form-aware`)
+    pub fn form_aware(mut self, on: bool) -> Self {
+        self.form_aware = on;
+        self
+    }
+
+    /// Skip describing secrets when only findings are needed (e.g. counting):
+    /// finding structures are empty and markers read `[REDACTED:LABEL:]`.
+    /// Detection is unchanged, so findings match a full redaction.
+    pub fn findings_only(mut self, on: bool) -> Self {
+        self.findings_only = on;
+        self
+    }
+
+    /// Minimum length of a context-pattern value (e.g. after `token=`) for it
+    /// to be redacted; shorter values are left as they are, and an empty or
+    /// blank value is never redacted, even with 0. Env values and direct
+    /// patterns are not affected.
+    pub fn min_context_len(mut self, len: usize) -> Self {
+        self.min_context_len = len;
+        self
+    }
+
+    /// On lines that look like JSON, run entropy detection on string values
+    /// only, skipping keys and the text between strings. Other lines are
+    /// checked as usual.
+    pub fn entropy_json_values_only(mut self, on: bool) -> Self {
+        self.entropy_json_values_only = on;
+        self
+    }
+
+    /// Entropy settings (default: [`EntropyConfig::default`])
+    pub fn entropy_config(mut self, config: EntropyConfig) -> Self {
+        self.entropy_config = Some(config);
+        self
+    }
+
+    /// Only redact these labels (env var names for values, `HIGH_ENTROPY`,
+    /// `PRIVATE_KEY`, or pattern labels); everything else passes through
+    pub fn only_labels(mut self, labels: impl IntoIterator<Item = String>) -> Self {
+        self.only_labels = Some(labels.into_iter().collect());
+        self
+    }
+
+    /// Register a direct pattern: every match of `regex` is redacted as `label`
+    pub fn add_pattern(mut self, label: &str, regex: &str) -> Result<Self, String> {
+        let regex =
+            Regex::new(regex).map_err(|e| format!("invalid pattern for {}: {}", label, e))?;
+        self.extra_patterns.push(Pattern {
+            regex,
+            label: label.to_string(),
+        });
+        Ok(self)
+    }
+
+    /// Register a context pattern: capture group 1 is the prefix kept in the
+    /// output, capture group `group` is the secret. Anything matched after the
+    /// secret group is kept too, so a trailing group works like lookahead.
+    pub fn add_context_pattern(
+        mut self,
+        label: &str,
+        regex: &str,
+        group: usize,
+    ) -> Result<Self, String> {
+        let regex =
+            Regex::new(regex).map_err(|e| format!("invalid pattern for {}: {}", label, e))?;
+        if group < 2 || group >= regex.captures_len() {
+            return Err(format!(
+                "invalid pattern for {}: secret group {} must be 2..={} (group 1 is the prefix)",
+                label,
+                group,
+                regex.captures_len().saturating_sub(1)
+            ));
+        }
+        if prefix_has_unbounded_dot(regex.as_str()) {
+            return Err(format!(
+                "invalid pattern for {}: prefix group must be bounded (no .* or .+)",
+                label
+            ));
+        }
+        self.extra_context_patterns.push(ContextPattern {
+            regex,
+            label: label.to_string(),
+            group,
+        });
+        Ok(self)
+    }
+
+    /// Never redact candidates fully matching `regex` (or whose surrounding
+    /// word does), whichever filter found them; use [`regex::escape`] for
+    /// literals
+    pub fn add_allow_regex(mut self, regex: &str) -> Result<Self, String> {
+        let regex = Regex::new(&format!("^(?:{})$", regex))
+            .map_err(|e| format!("invalid allow pattern: {}", e))?;
+        self.allow.push(regex);
+        Ok(self)
+    }
+
+    /// Never flag tokens fully matching `regex` as high entropy (known
+    /// non-secrets such as build hashes); use [`regex::escape`] for literals
+    pub fn add_entropy_allow(mut self, regex: &str) -> Result<Self, String> {
+        let regex = Regex::new(&format!("^(?:{})$", regex))
+            .map_err(|e| format!("invalid entropy allow pattern: {}", e))?;
+        self.entropy_allow.push(regex);
+        Ok(self)
+    }
+
+    /// Compile everything needed by the enabled filters
+    pub fn build(self) -> Redactor {
+        let (only_labels, mut config) = if self.private_keys_only {
+            let only = HashSet::from(["PRIVATE_KEY".to_string()]);
+            let none = FilterConfig {
+                values: false,
+                patterns: false,
+                entropy: false,
+            };
+            (Some(only), none)
+        } else {
+            (self.only_labels, self.config)
+        };
+        let enabled = |label: &str| only_labels.as_ref().is_none_or(|l| l.contains(label));
+
+        if !enabled("HIGH_ENTROPY") {
+            config.entropy = false;
+        }
+
+        let mut secrets = self.secrets;
+        secrets.retain(|name, _| enabled(name));
+
+        // Only compile patterns if the patterns filter is enabled
+        let (patterns, context_patterns) = if config.patterns {
+            let mut patterns = build_patterns();
+            patterns.extend(self.extra_patterns);
+            patterns.retain(|p| enabled(&p.label));
+            let mut context_patterns = build_context_patterns();
+            context_patterns.extend(self.extra_context_patterns);
+            context_patterns.retain(|p| enabled(&p.label));
+            (patterns, context_patterns)
+        } else {
+            (Vec::new(), Vec::new())
+        };
+
+        // Signature-only JWTs get their own pass before the patterns filter
+        let jwt_signature_re = (self.jwt_mode == JwtMode::SignatureOnly)
+            .then(|| patterns.iter().find(|p| p.label == JWT_LABEL))
+            .flatten()
+            .map(|p| p.regex.clone());
+
+        // Entropy configuration (only if entropy filter enabled)
+        let entropy_config = if config.entropy {
+            Some(self.entropy_config.unwrap_or_default())
+        } else {
+            None
+        };
+
+        // Build exclusion regexes for entropy detection
+        let exclusion_regexes = if config.entropy {
+            let mut exclusions = build_exclusion_regexes();
+            exclusions.extend(
+                self.entropy_allow
+                    .into_iter()
+                    .map(|re| (re, &ALLOWLIST_EXCLUSION)),
+            );
+            exclusions
+        } else {
+            Vec::new()
+        };
+
+        // Token delimiter regex for entropy detection (precompiled)
+        let token_delim_re = if config.entropy {
+            Some(Regex::new(r#"[\s"'`()\[\]{},;:<>=@#]+"#).unwrap())
+        } else {
+            None
+        };
+
+        // Private key blocks are part of the patterns filter
+        let private_key_markers =
+            if (config.patterns || self.private_keys_only) && enabled("PRIVATE_KEY") {
+                Some((
+                    Regex::new(PRIVATE_KEY_BEGIN).unwrap(),
+                    Regex::new(PRIVATE_KEY_END).unwrap(),
+                ))
+            } else {
+                None
+            };
+
+        Redactor {
+            config,
+            secrets: build_secret_values(secrets, self.whole_word_secrets),
+            patterns,
+            context_patterns,
+            // Special patterns (git credential, docker auth) belong to the patterns filter
+            special_patterns: build_special_patterns(|label| config.patterns && enabled(label)),
+            form_patterns: (config.patterns && self.form_aware && enabled(FORM_SECRET_LABEL))
+                .then(build_form_patterns),
+            obfuscated_token_re: (config.patterns && self.detect_obfuscated)
+                .then(|| Regex::new(OBFUSCATED_TOKEN_PATTERN).unwrap()),
+            jwt_signature_re,
+            email_re: self
+                .email_mode
+                .filter(|_| config.patterns && enabled(EMAIL_LABEL))
+                .map(|mode| (Regex::new(EMAIL_PATTERN).unwrap(), mode)),
+            workflow_command_re: self
+                .workflow_commands
+                .then(|| Regex::new(WORKFLOW_COMMAND_PATTERN).unwrap()),
+            json_split_re: (config.patterns && enabled(JSON_SECRET_LABEL)).then(|| {
+                (
+                    Regex::new(JSON_KEY_AT_EOL).unwrap(),
+                    Regex::new(JSON_VALUE_AT_BOL).unwrap(),
+                )
+            }),
+            mycnf_re: (config.patterns && enabled(MYCNF_PASSWORD_LABEL)).then(|| {
+                (
+                    Regex::new(MYCNF_GROUP).unwrap(),
+                    Regex::new(MYCNF_PASSWORD).unwrap(),
+                )
+            }),
+            k8s_env_re: (config.patterns && enabled(K8S_ENV_SECRET_LABEL)).then(|| {
+                (
+                    Regex::new(K8S_ENV_NAME).unwrap(),
+                    Regex::new(K8S_ENV_VALUE).unwrap(),
+                )
+            }),
+            fence: self.fence.filter(|_| !self.private_keys_only),
+            normalize_unicode: self.normalize_unicode,
+            entropy_keys_re: (!self.entropy_keys.is_empty() && enabled("HIGH_ENTROPY"))
+                .then(|| Regex::new(&entropy_keys_pattern(&self.entropy_keys)).unwrap()),
+            entropy_config,
+            exclusion_regexes,
+            token_delim_re,
+            json_string_re: (config.entropy && self.entropy_json_values_only)
+                .then(|| Regex::new(JSON_STRING_PATTERN).unwrap()),
+            env_dump_re: (config.values && self.env_dump)
+                .then(|| Regex::new(ENV_DUMP_PATTERN).unwrap()),
+            only_labels,
+            structure_format: self.structure_format,
+            min_context_len: self.min_context_len,
+            hash: self.hash,
+            fingerprint: self.fingerprint,
+            preserve_length: self.preserve_length,
+            merge_adjacent: self.merge_adjacent,
+            findings_only: self.findings_only,
+            entropy_precision: self.entropy_precision,
+            allow: self.allow,
+            marker_prefix: self.marker_prefix,
+            label_map: self.label_map,
+            echo_prefix: self.echo_prefix,
+            private_key_markers,
+            private_key_structure: self.private_key_structure,
+            private_key_type: String::new(),
+            stream: StreamState::Normal,
+            pending: Continuation::None,
+            mycnf_client: false,
+            chunk_tail: Vec::new(),
+            first_seen: self
+                .keep_first_occurrence
+                .then(|| SeenValues::new(self.first_occurrence_cap)),
+        }
+    }
+}
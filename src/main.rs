@@ -8,52 +8,889 @@
 //   SECRETS_FILTER_ENTROPY=1|true|yes  (ENV, enables entropy filter, off by default)
 //
 // Default: values + patterns enabled, entropy disabled. CLI overrides ENV entirely.
+//
+// Options, env vars and exit codes: `kahl --help` (help_text below) and the README.
+
+// KAHL_VERSION is set by build.rs; fall back to the manifest version if it is missing
+const VERSION: &str = match option_env!("KAHL_VERSION") {
+    Some(v) => v,
+    None => env!("CARGO_PKG_VERSION"),
+};
+
+/// Version string for display: trimmed, falling back to the manifest version if empty
+fn version() -> &'static str {
+    let v = VERSION.trim();
+    if v.is_empty() {
+        env!("CARGO_PKG_VERSION")
+    } else {
+        v
+    }
+}
 
-const VERSION: &str = env!("KAHL_VERSION");
+mod archive;
+mod benchmark;
+mod csv;
+mod journal;
+mod perf;
+mod progress;
+mod remote;
+mod sarif;
+
+use csv::CsvReport;
+use journal::JournalItem;
+use kahl::internal::line_ending;
+use kahl::{
+    EmailMode, EntropyCharset, EntropyConfig, EntropyModel, FilterConfig, FilterKind, Finding,
+    JwtMode, PRIVATE_KEY_BEGIN, PrivateKeyStructure, Redactor, RedactorBuilder, SecretHash,
+    StreamOutput, StructureFormat, is_falsy, is_truthy, strip_invisible,
+};
+use perf::Perf;
+use progress::Progress;
+use sarif::SarifReport;
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::io::{self, BufRead, IsTerminal, Read, Write};
+use std::time::{Duration, Instant};
+
+/// Leading `X.Y.Z` of a version as numbers (`v` prefix and `-`/`+` suffixes
+/// ignored; missing minor or patch count as 0)
+fn parse_semver(version: &str) -> Option<(u64, u64, u64)> {
+    let version = version.trim();
+    let version = version.strip_prefix('v').unwrap_or(version);
+    let core = version.split(['-', '+']).next()?;
+    let mut parts = core.split('.').map(|p| p.parse::<u64>().ok());
+    let major = parts.next()??;
+    let minor = parts.next().unwrap_or(Some(0))?;
+    let patch = parts.next().unwrap_or(Some(0))?;
+    if parts.next().is_some() {
+        return None;
+    }
+    Some((major, minor, patch))
+}
 
-mod patterns_gen;
-use patterns_gen::*;
+/// Check --min-version against the running binary
+fn check_min_version(required: &str) -> Result<(), String> {
+    let wanted = parse_semver(required)
+        .ok_or_else(|| format!("secrets-filter: invalid --min-version '{}'", required))?;
+    match parse_semver(version()) {
+        Some(current) if current >= wanted => Ok(()),
+        _ => Err(format!(
+            "secrets-filter: version {} is older than the required {}",
+            version(),
+            required.trim()
+        )),
+    }
+}
 
-use regex::Regex;
-use std::collections::{HashMap, HashSet};
-use std::env;
-use std::io::{self, BufRead, Write};
+/// Options parsed from the command line
+#[derive(Debug, Default)]
+struct CliOptions {
+    /// Raw --filter value; None means use SECRETS_FILTER_* env vars
+    filter: Option<String>,
+    /// --only-labels: redact only these labels, pass everything else through
+    only_labels: Option<Vec<String>>,
+    /// --secrets-from-stdin-header: consume a leading secrets block from stdin
+    secrets_header: bool,
+    /// --structure-format: how marker structures are rendered
+    structure_format: StructureFormat,
+    /// --output: redacted stream or a findings report
+    output: OutputFormat,
+    /// --min-secret-len: minimum context value length to redact
+    min_secret_len: Option<usize>,
+    /// --hash: hash secrets into markers instead of describing their structure
+    hash: bool,
+    /// --hash-salt / --hash-salt-file: HMAC key for hashed markers
+    hash_salt: Option<Vec<u8>>,
+    /// --fingerprint: keyed fingerprints after marker structures
+    fingerprint: bool,
+    /// --preserve-length: fixed-width markers
+    preserve_length: bool,
+    /// --merge-adjacent: one marker for a run of same-label markers
+    merge_adjacent: bool,
+    /// --entropy-allow-file: entropy allowlist regexes (literals escaped)
+    entropy_allow: Vec<String>,
+    /// --entropy-charset / --entropy-charset-file: custom entropy alphabets
+    entropy_charsets: Vec<EntropyCharset>,
+    /// --entropy-model: how high-entropy tokens are confirmed
+    entropy_model: EntropyModel,
+    /// --form-aware: pair-by-pair redaction of form bodies
+    form_aware: bool,
+    /// --dead-patterns: report patterns without matches at EOF
+    dead_patterns: bool,
+    /// --entropy-precision: None keeps the default, Some(None) omits the score
+    entropy_precision: Option<Option<usize>>,
+    /// --allow / --allow-regex: allowlist regexes (literals escaped)
+    allow: Vec<String>,
+    /// --entropy-keys: keys whose values are always redacted as HIGH_ENTROPY
+    entropy_keys: Vec<String>,
+    /// --normalize-unicode: strip invisible characters before redaction
+    normalize_unicode: bool,
+    /// --redact-between: START and END sentinels of fenced regions
+    redact_between: Option<(String, String)>,
+    /// --only-private-keys: run only the private key state machine
+    only_private_keys: bool,
+    /// --detect-obfuscated: check reversed tokens too
+    detect_obfuscated: bool,
+    /// --jwt-mode: whole JWTs or only their signatures
+    jwt_mode: JwtMode,
+    /// --email-mode: redact email addresses, whole or in part
+    email_mode: Option<EmailMode>,
+    /// --redact-uuids: which UUIDs to redact
+    redact_uuids: UuidRedaction,
+    /// --preset: curated option set for an environment
+    preset: Option<Preset>,
+    /// --color / --no-color: marker highlighting
+    color: ColorChoice,
+    /// --marker-env: env var whose value prefixes marker labels
+    marker_env: Option<String>,
+    /// --private-key-structure: private key marker structure
+    private_key_structure: PrivateKeyStructure,
+    /// --label-map / --label-map-file: marker names for labels
+    label_map: Vec<(String, String)>,
+    /// --no-prefix-echo: never show a token's first segment literally
+    no_prefix_echo: bool,
+    /// --journal: journal export format input
+    journal: bool,
+    /// --tar: tar archive input
+    tar: bool,
+    /// --count-only: print the number of findings instead of the stream
+    count_only: bool,
+    /// --stats: per-label counts (also SECRETS_FILTER_STATS=1)
+    stats: bool,
+    /// --diff: before/after of changed lines on stderr
+    diff: bool,
+    /// --perf: timing summary on stderr at EOF
+    perf: bool,
+    /// --benchmark: throughput report on synthetic input
+    benchmark: bool,
+    /// --progress: periodic status on stderr
+    progress: ProgressChoice,
+    /// --progress-interval: milliseconds between progress reports
+    progress_interval: Option<u64>,
+    /// --whole-word-secrets: env values only match as whole words
+    whole_word_secrets: bool,
+    /// --redact-all-high-entropy-in-json-values: entropy on JSON values only
+    entropy_json_values: bool,
+    /// --env-dump: redact sensitive NAME=value lines
+    env_dump: bool,
+    /// --dump-unmatched-high-entropy: report skipped entropy candidates
+    dump_unmatched_entropy: bool,
+    /// --fail-on-findings: exit 1 if anything was redacted
+    fail_on_findings: bool,
+    /// --max-input-bytes: stop reading after this many bytes
+    max_input_bytes: Option<u64>,
+    /// --sample-rate: fraction of lines scanned, None scans all
+    sample_rate: Option<f64>,
+    /// --sample-seed: seed of the line sampling
+    sample_seed: Option<u64>,
+    /// --exclude-line-regex: lines passed through untouched
+    exclude_lines: Vec<String>,
+    /// --unsafe-keep-first: first occurrence of each secret left readable
+    unsafe_keep_first: bool,
+    /// --unsafe-keep-first-cap: most distinct values remembered
+    unsafe_keep_first_cap: Option<usize>,
+    /// --patterns-url: centrally managed pattern file
+    patterns_url: Option<String>,
+    /// --patterns-url-optional: run without them if the fetch fails
+    patterns_url_optional: bool,
+    /// --pre-commit: report findings in files instead of filtering stdin
+    pre_commit: bool,
+    /// Positional arguments: files to scan with --pre-commit
+    files: Vec<String>,
+    /// --input / @PATH / -: where to read the stream ("-" is stdin)
+    input: Option<String>,
+    /// --parallel: worker threads for the line stream
+    parallel: Option<usize>,
+}
+
+/// Process exit status by outcome
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ExitCode {
+    /// Nothing found, or findings without --fail-on-findings
+    Clean = 0,
+    /// Findings with --fail-on-findings (always for --pre-commit)
+    Findings = 1,
+    /// Invalid options or filter configuration
+    Config = 2,
+    /// A user-supplied regex (--allow-regex, allow files) does not compile
+    PatternCompile = 3,
+    /// Input or a scanned file could not be read
+    Io = 4,
+}
+
+impl ExitCode {
+    fn exit(self) -> ! {
+        std::process::exit(self as i32)
+    }
+}
+
+/// What is written to stdout
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    /// The redacted input stream
+    #[default]
+    Text,
+    /// A SARIF report of findings; the stream itself is suppressed
+    Sarif,
+    /// A CSV table of findings; the stream itself is suppressed
+    Csv,
+}
+
+/// Which UUIDs --redact-uuids redacts
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum UuidRedaction {
+    /// None (UUIDs are also excluded from entropy detection)
+    #[default]
+    Off,
+    /// Every UUID
+    All,
+    /// Only UUIDs right after a secret-ish key
+    Context,
+}
+
+/// A UUID in any case, as a whole word
+const UUID_PATTERN: &str =
+    r"\b[0-9A-Fa-f]{8}-[0-9A-Fa-f]{4}-[0-9A-Fa-f]{4}-[0-9A-Fa-f]{4}-[0-9A-Fa-f]{12}\b";
+
+/// A UUID after a key containing token, secret, key, password, credential,
+/// auth or session (`api_key=`, `X-Session-Id: `, `"authToken": "`)
+const UUID_CONTEXT_PATTERN: &str = concat!(
+    r#"(\b[A-Za-z0-9_.-]*(?i-u:token|secret|key|password|passwd|pwd|credential|auth|session)"#,
+    r#"[A-Za-z0-9_.-]*["']?\s*[=:]?\s*["']?)"#,
+    r"([0-9A-Fa-f]{8}-[0-9A-Fa-f]{4}-[0-9A-Fa-f]{4}-[0-9A-Fa-f]{4}-[0-9A-Fa-f]{12})\b"
+);
+
+/// Parse a --redact-uuids value
+fn parse_redact_uuids(val: &str) -> Result<UuidRedaction, String> {
+    match val.trim().to_lowercase().as_str() {
+        "all" => Ok(UuidRedaction::All),
+        "context" => Ok(UuidRedaction::Context),
+        other => Err(format!(
+            "secrets-filter: unknown --redact-uuids mode '{}' (expected all or context)",
+            other
+        )),
+    }
+}
+
+/// Curated option sets (--preset)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Preset {
+    /// GitHub Actions logs, for what the runner's own masking misses
+    GithubActions,
+}
+
+/// Value of an `::add-mask::` command, echoed into the log when the command
+/// did not take effect (inside a set -x trace or a quoted echo)
+const ACTIONS_ADD_MASK_PATTERN: &str = r#"(::add-mask::)([^\s'"]+)"#;
+
+/// Secret env var assigned in a set -x trace: `+ export DEPLOY_TOKEN=...`
+const ACTIONS_TRACE_PATTERN: &str = concat!(
+    r"^(\++ (?:export |declare -x |local |readonly )?[A-Za-z0-9_]*",
+    r#"(?:SECRET|PASSWORD|TOKEN|API_KEY|PRIVATE_KEY|AUTH|CREDENTIAL)[A-Za-z0-9_]*=['"]?)"#,
+    r#"([^\s'"]+)"#
+);
+
+/// Rest of a secret right after a `***` mask (only part of it was registered)
+const ACTIONS_MASK_TAIL_PATTERN: &str = r"(\*\*\*)([A-Za-z0-9_+/=-]{4,})";
+
+/// Start of a secret right before a `***` mask
+const ACTIONS_MASK_HEAD_PATTERN: &str = r"(^|[^A-Za-z0-9_+/=*-])([A-Za-z0-9_+/=-]{4,})\*\*\*";
+
+/// Parse a --preset name
+fn parse_preset(val: &str) -> Result<Preset, String> {
+    match val.trim().to_lowercase().as_str() {
+        "github-actions" => Ok(Preset::GithubActions),
+        other => Err(format!(
+            "secrets-filter: unknown preset '{}' (expected github-actions)",
+            other
+        )),
+    }
+}
+
+/// When markers are highlighted
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum ColorChoice {
+    /// Only when stdout is a terminal and NO_COLOR is unset
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// Resolve against stdout and the NO_COLOR convention (https://no-color.org)
+    fn enabled(self) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none(),
+        }
+    }
+}
+
+/// When --progress reports
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum ProgressChoice {
+    #[default]
+    Never,
+    /// Only when stderr is a terminal
+    Auto,
+    /// Also to a file or pipe, one line per report
+    Always,
+}
+
+/// Parse a --progress value
+fn parse_progress(val: &str) -> Result<ProgressChoice, String> {
+    match val.trim().to_lowercase().as_str() {
+        "auto" => Ok(ProgressChoice::Auto),
+        "always" => Ok(ProgressChoice::Always),
+        "never" => Ok(ProgressChoice::Never),
+        other => Err(format!(
+            "secrets-filter: unknown progress mode '{}' (expected auto, always or never)",
+            other
+        )),
+    }
+}
+
+/// Parse a --progress-interval value (positive milliseconds)
+fn parse_progress_interval(val: &str) -> Result<u64, String> {
+    match val.trim().parse() {
+        Ok(ms) if ms > 0 => Ok(ms),
+        _ => Err(format!(
+            "secrets-filter: invalid --progress-interval '{}'",
+            val
+        )),
+    }
+}
+
+/// Parse a --parallel worker count (positive)
+fn parse_parallel(val: &str) -> Result<usize, String> {
+    match val.trim().parse() {
+        Ok(n) if n > 0 => Ok(n),
+        _ => Err(format!("secrets-filter: invalid --parallel '{}'", val)),
+    }
+}
+
+/// Parse an --unsafe-keep-first-cap value (a positive count)
+fn parse_keep_first_cap(val: &str) -> Result<usize, String> {
+    match val.trim().parse() {
+        Ok(n) if n > 0 => Ok(n),
+        _ => Err(format!(
+            "secrets-filter: invalid --unsafe-keep-first-cap '{}' (expected a positive count)",
+            val
+        )),
+    }
+}
+
+/// Parse a --color value
+fn parse_color(val: &str) -> Result<ColorChoice, String> {
+    match val.trim().to_lowercase().as_str() {
+        "auto" => Ok(ColorChoice::Auto),
+        "always" => Ok(ColorChoice::Always),
+        "never" => Ok(ColorChoice::Never),
+        other => Err(format!(
+            "secrets-filter: unknown color mode '{}' (expected auto, always or never)",
+            other
+        )),
+    }
+}
+
+/// Read a salt file, dropping the trailing newline
+fn read_salt_file(path: &str) -> Result<Vec<u8>, String> {
+    let mut salt = std::fs::read(path)
+        .map_err(|e| format!("secrets-filter: cannot read salt file '{}': {}", path, e))?;
+    while salt.last().is_some_and(|b| *b == b'\n' || *b == b'\r') {
+        salt.pop();
+    }
+    Ok(salt)
+}
+
+/// Read an entropy allowlist file into regexes
+///
+/// `re:` lines are regexes, other lines literals; blank lines and `#`
+/// comments are skipped.
+fn read_entropy_allow_file(path: &str) -> Result<Vec<String>, String> {
+    let content = std::fs::read_to_string(path).map_err(|e| {
+        format!(
+            "secrets-filter: cannot read entropy allow file '{}': {}",
+            path, e
+        )
+    })?;
+    Ok(content
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(|l| match l.strip_prefix("re:") {
+            Some(re) => re.to_string(),
+            None => regex::escape(l),
+        })
+        .collect())
+}
+
+/// Charset names of the builtin classification and their marker forms
+const BUILTIN_CHARSETS: &[&str] = &[
+    "hex",
+    "base64",
+    "b64",
+    "alphanumeric",
+    "alnum",
+    "mixed",
+    "mix",
+];
+
+/// Parse an --entropy-charset value: NAME:THRESHOLD:ALPHABET
+fn parse_entropy_charset(val: &str) -> Result<EntropyCharset, String> {
+    let mut parts = val.splitn(3, ':');
+    let (name, threshold, alphabet) = (parts.next(), parts.next(), parts.next());
+    let charset = match (name, threshold.map(|t| t.trim().parse::<f64>()), alphabet) {
+        (Some(name), Some(Ok(threshold)), Some(alphabet))
+            if !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-'))
+                && threshold > 0.0
+                && !alphabet.is_empty() =>
+        {
+            EntropyCharset {
+                name: name.to_string(),
+                alphabet: alphabet.to_string(),
+                threshold,
+            }
+        }
+        _ => {
+            return Err(format!(
+                "secrets-filter: invalid --entropy-charset '{}' (expected \
+                 NAME:THRESHOLD:ALPHABET, NAME of letters, digits, '_' or '-')",
+                val
+            ));
+        }
+    };
+    if BUILTIN_CHARSETS.contains(&charset.name.as_str()) {
+        return Err(format!(
+            "secrets-filter: --entropy-charset: '{}' is a builtin charset name",
+            charset.name
+        ));
+    }
+    Ok(charset)
+}
+
+/// Read an --entropy-charset-file: one NAME:THRESHOLD:ALPHABET per line,
+/// blank lines and `#` comments skipped
+fn read_entropy_charset_file(path: &str) -> Result<Vec<EntropyCharset>, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("secrets-filter: cannot read charset file '{}': {}", path, e))?;
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(parse_entropy_charset)
+        .collect()
+}
+
+/// Parse a --private-key-structure value: `multiline`, or `type`, `lines`
+/// joined with `+`
+fn parse_private_key_structure(val: &str) -> Result<PrivateKeyStructure, String> {
+    let mut structure = PrivateKeyStructure::default();
+    let val = val.trim().to_lowercase();
+    if val == "multiline" {
+        return Ok(structure);
+    }
+    for part in val.split('+') {
+        match part.trim() {
+            "type" => structure.key_type = true,
+            "lines" => structure.lines = true,
+            _ => {
+                return Err(format!(
+                    "secrets-filter: invalid --private-key-structure '{}' (expected \
+                     multiline, or type and/or lines joined with '+')",
+                    val
+                ));
+            }
+        }
+    }
+    Ok(structure)
+}
+
+/// Parse an OLD=NEW --label-map entry; NEW must not break the marker syntax
+fn parse_label_map(val: &str) -> Result<(String, String), String> {
+    match val.split_once('=') {
+        Some((old, new))
+            if !old.trim().is_empty()
+                && !new.trim().is_empty()
+                && !new
+                    .trim()
+                    .contains(|c: char| c.is_whitespace() || matches!(c, '[' | ']')) =>
+        {
+            Ok((old.trim().to_string(), new.trim().to_string()))
+        }
+        _ => Err(format!(
+            "secrets-filter: invalid --label-map '{}' (expected OLD=NEW, NEW without \
+             whitespace or brackets)",
+            val
+        )),
+    }
+}
+
+/// Read a --label-map-file: OLD=NEW per line, blank lines and `#` comments
+/// skipped
+fn read_label_map_file(path: &str) -> Result<Vec<(String, String)>, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("secrets-filter: cannot read label map '{}': {}", path, e))?;
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(parse_label_map)
+        .collect()
+}
+
+/// One entry of a --patterns-url file
+struct RemotePattern {
+    label: String,
+    regex: String,
+    /// Prefix in group 1, secret in group 2
+    context: bool,
+}
+
+/// Parse a patterns file: `LABEL=REGEX` lines, `context LABEL=REGEX` for
+/// context patterns; blank lines and `#` comments are skipped
+fn parse_patterns_file(content: &str) -> Result<Vec<RemotePattern>, String> {
+    let mut patterns = Vec::new();
+    for line in content.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (context, entry) = match line.strip_prefix("context ") {
+            Some(rest) => (true, rest.trim_start()),
+            None => (false, line),
+        };
+        let (label, regex) = entry
+            .split_once('=')
+            .filter(|(label, regex)| {
+                !label.is_empty()
+                    && !regex.is_empty()
+                    && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+            })
+            .ok_or_else(|| format!("invalid patterns line '{}' (expected LABEL=REGEX)", line))?;
+        patterns.push(RemotePattern {
+            label: label.to_string(),
+            regex: regex.to_string(),
+            context,
+        });
+    }
+    Ok(patterns)
+}
+
+/// Fetch the --patterns-url file, falling back to the cached copy; exits
+/// unless it is --patterns-url-optional
+fn load_remote_patterns(url: &str, optional: bool) -> Vec<RemotePattern> {
+    let content = match remote::fetch(url) {
+        Ok(content) => {
+            remote::store(url, &content);
+            content
+        }
+        Err(e) => match remote::cached(url) {
+            Some(content) => {
+                eprintln!(
+                    "secrets-filter: warning: cannot fetch --patterns-url ({}); using the cached copy",
+                    e
+                );
+                content
+            }
+            None if optional => {
+                eprintln!(
+                    "secrets-filter: warning: cannot fetch --patterns-url ({}); continuing without it",
+                    e
+                );
+                return Vec::new();
+            }
+            None => {
+                eprintln!(
+                    "secrets-filter: cannot fetch --patterns-url '{}': {}",
+                    url, e
+                );
+                ExitCode::Io.exit();
+            }
+        },
+    };
+    parse_patterns_file(&content).unwrap_or_else(|e| {
+        eprintln!("secrets-filter: --patterns-url: {}", e);
+        ExitCode::PatternCompile.exit();
+    })
+}
+
+/// Parse an --entropy-precision value (`none` omits the score)
+fn parse_entropy_precision(val: &str) -> Result<Option<usize>, String> {
+    let val = val.trim();
+    if val.eq_ignore_ascii_case("none") {
+        return Ok(None);
+    }
+    val.parse()
+        .map(Some)
+        .map_err(|_| format!("secrets-filter: invalid --entropy-precision '{}'", val))
+}
+
+/// Compile user-supplied regexes up front, so a typo aborts before stdin is
+/// read (the builder anchors them, which cannot break a valid regex)
+fn check_user_patterns(opts: &CliOptions) -> Result<(), String> {
+    for re in &opts.allow {
+        regex::Regex::new(re)
+            .map_err(|e| format!("secrets-filter: invalid --allow-regex '{}': {}", re, e))?;
+    }
+    for re in &opts.entropy_allow {
+        regex::Regex::new(re).map_err(|e| {
+            format!(
+                "secrets-filter: invalid entropy allow pattern '{}': {}",
+                re, e
+            )
+        })?;
+    }
+    Ok(())
+}
+
+/// Parse a --min-secret-len value
+fn parse_min_secret_len(val: &str) -> Result<usize, String> {
+    val.trim()
+        .parse()
+        .map_err(|_| format!("secrets-filter: invalid --min-secret-len '{}'", val))
+}
+
+/// Parse a --max-input-bytes value (a positive byte count)
+fn parse_max_input_bytes(val: &str) -> Result<u64, String> {
+    match val.trim().parse() {
+        Ok(n) if n > 0 => Ok(n),
+        _ => Err(format!(
+            "secrets-filter: invalid --max-input-bytes '{}'",
+            val
+        )),
+    }
+}
+
+/// Parse a --sample-rate value (a fraction in (0, 1])
+fn parse_sample_rate(val: &str) -> Result<f64, String> {
+    match val.trim().parse::<f64>() {
+        Ok(r) if r > 0.0 && r <= 1.0 => Ok(r),
+        _ => Err(format!(
+            "secrets-filter: invalid --sample-rate '{}' (expected a fraction in (0, 1])",
+            val
+        )),
+    }
+}
+
+/// Parse a --sample-seed value
+fn parse_sample_seed(val: &str) -> Result<u64, String> {
+    val.trim()
+        .parse()
+        .map_err(|_| format!("secrets-filter: invalid --sample-seed '{}'", val))
+}
+
+/// Whether --sample-rate scans line `line_no`: a hash of seed and line
+/// number (splitmix64), so runs with the same seed scan the same lines
+fn sampled(rate: f64, seed: u64, line_no: usize) -> bool {
+    let mut z = seed ^ (line_no as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^= z >> 31;
+    ((z >> 11) as f64 / (1u64 << 53) as f64) < rate
+}
+
+/// Parse an --output value
+fn parse_output_format(val: &str) -> Result<OutputFormat, String> {
+    match val.trim().to_lowercase().as_str() {
+        "text" => Ok(OutputFormat::Text),
+        "sarif" => Ok(OutputFormat::Sarif),
+        "csv" => Ok(OutputFormat::Csv),
+        other => Err(format!(
+            "secrets-filter: unknown output format '{}' (expected text, sarif or csv)",
+            other
+        )),
+    }
+}
 
-#[derive(Debug, Clone, Copy)]
-struct FilterConfig {
-    values: bool,
-    patterns: bool,
-    entropy: bool,
+/// Parse a --structure-format value
+fn parse_structure_format(val: &str) -> Result<StructureFormat, String> {
+    match val.trim().to_lowercase().as_str() {
+        "compact" => Ok(StructureFormat::Compact),
+        "verbose" => Ok(StructureFormat::Verbose),
+        other => Err(format!(
+            "secrets-filter: unknown structure format '{}' (expected compact or verbose)",
+            other
+        )),
+    }
+}
+
+/// Parse an --entropy-model value
+fn parse_entropy_model(val: &str) -> Result<EntropyModel, String> {
+    match val.trim().to_lowercase().as_str() {
+        "shannon" => Ok(EntropyModel::Shannon),
+        "bigram" => Ok(EntropyModel::Bigram),
+        other => Err(format!(
+            "secrets-filter: unknown entropy model '{}' (expected shannon or bigram)",
+            other
+        )),
+    }
 }
 
-impl Default for FilterConfig {
-    fn default() -> Self {
-        Self {
-            values: true,
-            patterns: true,
-            entropy: ENTROPY_ENABLED_DEFAULT,
+/// Parse a --jwt-mode value
+fn parse_jwt_mode(val: &str) -> Result<JwtMode, String> {
+    match val.trim().to_lowercase().as_str() {
+        "full" => Ok(JwtMode::Full),
+        "signature-only" => Ok(JwtMode::SignatureOnly),
+        other => Err(format!(
+            "secrets-filter: unknown JWT mode '{}' (expected full or signature-only)",
+            other
+        )),
+    }
+}
+
+/// Parse an --email-mode value
+fn parse_email_mode(val: &str) -> Result<EmailMode, String> {
+    match val.trim().to_lowercase().as_str() {
+        "full" => Ok(EmailMode::Full),
+        "local" => Ok(EmailMode::Local),
+        "domain" => Ok(EmailMode::Domain),
+        other => Err(format!(
+            "secrets-filter: unknown email mode '{}' (expected full, local or domain)",
+            other
+        )),
+    }
+}
+
+/// Record the input source of --input, @PATH or -; only one is allowed
+fn set_input(opts: &mut CliOptions, path: &str) -> Result<(), String> {
+    if path.is_empty() {
+        return Err("secrets-filter: --input needs a path (or - for stdin)".to_string());
+    }
+    if let Some(prev) = &opts.input {
+        return Err(format!(
+            "secrets-filter: more than one input ('{}' and '{}')",
+            prev, path
+        ));
+    }
+    opts.input = Some(path.to_string());
+    Ok(())
+}
+
+/// Parse a --redact-between value: START,END (split at the first comma)
+fn parse_redact_between(val: &str) -> Result<(String, String), String> {
+    match val.split_once(',') {
+        Some((start, end)) if !start.is_empty() && !end.is_empty() => {
+            Ok((start.to_string(), end.to_string()))
         }
+        _ => Err(format!(
+            "secrets-filter: invalid --redact-between '{}' (expected START,END)",
+            val
+        )),
     }
 }
 
-/// Check if a string value is falsy (0, false, no)
-fn is_falsy(val: &str) -> bool {
-    matches!(val.to_lowercase().as_str(), "0" | "false" | "no")
+/// Split a comma-separated option value, dropping empty entries
+fn split_list(val: &str) -> Vec<String> {
+    val.split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
 }
 
-/// Check if a string value is truthy (1, true, yes)
-fn is_truthy(val: &str) -> bool {
-    matches!(val.to_lowercase().as_str(), "1" | "true" | "yes")
+/// Take the value following a flag given as `--flag value`
+fn next_value(args: &[String], i: &mut usize, flag: &str) -> Result<String, String> {
+    *i += 1;
+    args.get(*i)
+        .cloned()
+        .ok_or_else(|| format!("secrets-filter: option '{}' requires a value", flag))
 }
 
-/// Parse filter configuration from CLI args and environment
-fn parse_filter_config() -> Result<FilterConfig, String> {
+/// Help screen for --help: filters, common options, environment, exit codes
+fn help_text() -> String {
+    let entropy = EntropyConfig::default();
+    format!(
+        "\
+kahl {version} - redact secrets from a stream before it is stored or shared
+
+Usage: COMMAND | kahl [OPTIONS]
+       kahl [OPTIONS] --input=PATH
+
+Reads stdin (or --input) line by line and writes it to stdout with secrets
+replaced by markers such as [REDACTED:GITHUB_PAT:ghp_36X].
+
+Filters:
+  values    secret values from the environment (explicit names and names
+            ending in _TOKEN, _SECRET, _PASSWORD, ...), wherever they occur
+  patterns  known token formats (GitHub, Slack, AWS, OpenAI, ...), key=value
+            context (password=..., Authorization: ...) and private key blocks
+  entropy   random-looking tokens above a Shannon entropy threshold
+
+  -f, --filter=LIST  comma-separated filters to run: values, patterns,
+                     entropy, all (the three) or none (pass input through);
+                     -f X and -f=X are short forms. Overrides the
+                     SECRETS_FILTER_VALUES/PATTERNS/ENTROPY variables.
+  Default: values and patterns; entropy is off.
+
+Common options:
+  --only-labels=A,B        redact only these labels
+  --allow=S                never redact the literal S (repeatable)
+  --allow-regex=RE         never redact candidates fully matching RE
+  --structure-format=compact|verbose
+                           marker structure style
+  --hash, --hash-salt=S    replace marker structures with a (keyed) hash
+  --output=text|sarif|csv  redacted stream (default) or a findings report
+  --count-only [--stats]   print only the number of findings (per label)
+  --stats                  summarize findings per label on stderr at the end
+  --fail-on-findings       exit 1 if anything was redacted
+  --input=PATH, @PATH      read PATH instead of stdin; - reads stdin
+  --pre-commit [FILE...]   report findings in files as file:line: label
+  --journal, --tar         input is a journal export or a tar archive
+  --preset=github-actions  tuned for GitHub Actions logs
+  -v, --version            print the version
+  -h, --help               print this help
+  All options are described in the README.
+
+Environment:
+  SECRETS_FILTER_VALUES=0|false|no    disable the values filter (default on)
+  SECRETS_FILTER_PATTERNS=0|false|no  disable the patterns filter (default on)
+  SECRETS_FILTER_ENTROPY=1|true|yes   enable the entropy filter (default off)
+  SECRETS_FILTER_ENTROPY_THRESHOLD=N  one threshold for every charset
+  SECRETS_FILTER_ENTROPY_HEX=N        threshold for hex tokens (default {hex:.1})
+  SECRETS_FILTER_ENTROPY_BASE64=N     threshold for base64 tokens (default {base64:.1})
+  SECRETS_FILTER_ENTROPY_MIN_LEN=N    shortest token checked (default {min_len})
+  SECRETS_FILTER_ENTROPY_MAX_LEN=N    longest token checked (default {max_len})
+  SECRETS_FILTER_ENTROPY_SAMPLE_LONG=1
+                                      score longer tokens on a prefix sample
+                                      (default off)
+  SECRETS_FILTER_ENTROPY_KEYWORDS=[+]A,B
+                                      entropy context keywords; + adds to the
+                                      builtin list (default builtin list)
+  SECRETS_FILTER_STATS=1|true|yes     same as --stats (default off)
+  --filter on the command line overrides the first three.
+
+Exit codes:
+  0  clean, or findings without --fail-on-findings
+  1  findings (--fail-on-findings, --pre-commit)
+  2  invalid options
+  3  invalid user pattern
+  4  input could not be read
+
+Examples:
+  make 2>&1 | kahl
+  kubectl get secret app -o yaml | kahl --filter=all
+  git log -p | kahl --count-only --stats
+",
+        version = version(),
+        hex = entropy.threshold_hex,
+        base64 = entropy.threshold_base64,
+        min_len = entropy.min_length,
+        max_len = entropy.max_length,
+    )
+}
+
+/// Parse command-line arguments
+fn parse_args() -> Result<CliOptions, String> {
     let args: Vec<String> = env::args().collect();
 
     // Check for --version or -v
     for arg in &args[1..] {
         if arg == "--version" || arg == "-v" {
-            print!("{}", VERSION);
+            println!("kahl {}", version());
             std::process::exit(0);
         }
     }
@@ -61,54 +898,407 @@ fn parse_filter_config() -> Result<FilterConfig, String> {
     // Check for --help or -h
     for arg in &args[1..] {
         if arg == "--help" || arg == "-h" {
-            // TODO: print help text
+            // Written directly: `kahl --help | head` must not panic on EPIPE
+            let _ = io::stdout().write_all(help_text().as_bytes());
             std::process::exit(0);
         }
     }
 
-    // Validate all arguments - reject unknown flags
+    // Collect options - reject unknown flags
+    let mut opts = CliOptions::default();
     let mut i = 1;
     while i < args.len() {
         let arg = &args[i];
-        if arg.starts_with('-') {
-            // Check if it's a known flag
-            let is_known = arg == "-v"
-                || arg == "--version"
-                || arg == "-h"
-                || arg == "--help"
-                || arg == "-f"
-                || arg == "--filter"
-                || arg.starts_with("--filter=");
-
-            if !is_known {
-                eprintln!("Error: Unknown option: {}", arg);
-                eprintln!("Try 'kahl --help' for more information.");
-                std::process::exit(1);
-            }
-
-            // Skip next arg if this is -f or --filter (they take a value)
-            if arg == "-f" || arg == "--filter" {
-                i += 1;
+        if let Some(val) = arg
+            .strip_prefix("--filter=")
+            .or_else(|| arg.strip_prefix("-f="))
+        {
+            // First --filter wins
+            if opts.filter.is_none() {
+                opts.filter = Some(val.to_string());
             }
+        } else if arg == "-f" || arg == "--filter" {
+            // Skip next arg (the value)
+            if i + 1 < args.len() && opts.filter.is_none() {
+                opts.filter = Some(args[i + 1].clone());
+            }
+            i += 1;
+        } else if let Some(val) = arg.strip_prefix("--only-labels=") {
+            opts.only_labels = Some(split_list(val));
+        } else if arg == "--only-labels" {
+            opts.only_labels = Some(split_list(&next_value(&args, &mut i, arg)?));
+        } else if let Some(val) = arg.strip_prefix("--structure-format=") {
+            opts.structure_format = parse_structure_format(val)?;
+        } else if arg == "--structure-format" {
+            opts.structure_format = parse_structure_format(&next_value(&args, &mut i, arg)?)?;
+        } else if let Some(val) = arg.strip_prefix("--min-secret-len=") {
+            opts.min_secret_len = Some(parse_min_secret_len(val)?);
+        } else if arg == "--min-secret-len" {
+            opts.min_secret_len = Some(parse_min_secret_len(&next_value(&args, &mut i, arg)?)?);
+        } else if arg == "--preserve-length" {
+            opts.preserve_length = true;
+        } else if arg == "--merge-adjacent" {
+            opts.merge_adjacent = true;
+        } else if arg == "--hash" {
+            opts.hash = true;
+        } else if arg == "--fingerprint" {
+            opts.fingerprint = true;
+        } else if let Some(val) = arg.strip_prefix("--hash-salt=") {
+            opts.hash_salt = Some(val.as_bytes().to_vec());
+        } else if arg == "--hash-salt" {
+            opts.hash_salt = Some(next_value(&args, &mut i, arg)?.into_bytes());
+        } else if let Some(val) = arg.strip_prefix("--hash-salt-file=") {
+            opts.hash_salt = Some(read_salt_file(val)?);
+        } else if arg == "--hash-salt-file" {
+            opts.hash_salt = Some(read_salt_file(&next_value(&args, &mut i, arg)?)?);
+        } else if let Some(val) = arg.strip_prefix("--output=") {
+            opts.output = parse_output_format(val)?;
+        } else if arg == "--output" {
+            opts.output = parse_output_format(&next_value(&args, &mut i, arg)?)?;
+        } else if let Some(val) = arg.strip_prefix("--entropy-charset=") {
+            opts.entropy_charsets.push(parse_entropy_charset(val)?);
+        } else if arg == "--entropy-charset" {
+            let val = next_value(&args, &mut i, arg)?;
+            opts.entropy_charsets.push(parse_entropy_charset(&val)?);
+        } else if let Some(val) = arg.strip_prefix("--entropy-charset-file=") {
+            opts.entropy_charsets
+                .extend(read_entropy_charset_file(val)?);
+        } else if arg == "--entropy-charset-file" {
+            let path = next_value(&args, &mut i, arg)?;
+            opts.entropy_charsets
+                .extend(read_entropy_charset_file(&path)?);
+        } else if let Some(val) = arg.strip_prefix("--entropy-model=") {
+            opts.entropy_model = parse_entropy_model(val)?;
+        } else if arg == "--entropy-model" {
+            opts.entropy_model = parse_entropy_model(&next_value(&args, &mut i, arg)?)?;
+        } else if let Some(val) = arg.strip_prefix("--entropy-allow-file=") {
+            opts.entropy_allow.extend(read_entropy_allow_file(val)?);
+        } else if arg == "--entropy-allow-file" {
+            let path = next_value(&args, &mut i, arg)?;
+            opts.entropy_allow.extend(read_entropy_allow_file(&path)?);
+        } else if arg == "--form-aware" {
+            opts.form_aware = true;
+        } else if arg == "--dead-patterns" {
+            opts.dead_patterns = true;
+        } else if let Some(val) = arg.strip_prefix("--entropy-precision=") {
+            opts.entropy_precision = Some(parse_entropy_precision(val)?);
+        } else if arg == "--entropy-precision" {
+            opts.entropy_precision =
+                Some(parse_entropy_precision(&next_value(&args, &mut i, arg)?)?);
+        } else if let Some(val) = arg.strip_prefix("--allow=") {
+            opts.allow.push(regex::escape(val));
+        } else if arg == "--allow" {
+            opts.allow
+                .push(regex::escape(&next_value(&args, &mut i, arg)?));
+        } else if let Some(val) = arg.strip_prefix("--entropy-keys=") {
+            opts.entropy_keys.extend(split_list(val));
+        } else if arg == "--entropy-keys" {
+            opts.entropy_keys
+                .extend(split_list(&next_value(&args, &mut i, arg)?));
+        } else if arg == "--normalize-unicode" {
+            opts.normalize_unicode = true;
+        } else if let Some(val) = arg.strip_prefix("--redact-between=") {
+            opts.redact_between = Some(parse_redact_between(val)?);
+        } else if arg == "--redact-between" {
+            opts.redact_between = Some(parse_redact_between(&next_value(&args, &mut i, arg)?)?);
+        } else if arg == "--only-private-keys" {
+            opts.only_private_keys = true;
+        } else if let Some(val) = arg.strip_prefix("--patterns-url=") {
+            opts.patterns_url = Some(val.to_string());
+        } else if arg == "--patterns-url" {
+            opts.patterns_url = Some(next_value(&args, &mut i, arg)?);
+        } else if arg == "--unsafe-keep-first" {
+            opts.unsafe_keep_first = true;
+        } else if let Some(val) = arg.strip_prefix("--unsafe-keep-first-cap=") {
+            opts.unsafe_keep_first_cap = Some(parse_keep_first_cap(val)?);
+        } else if arg == "--unsafe-keep-first-cap" {
+            opts.unsafe_keep_first_cap =
+                Some(parse_keep_first_cap(&next_value(&args, &mut i, arg)?)?);
+        } else if arg == "--patterns-url-optional" {
+            opts.patterns_url_optional = true;
+        } else if let Some(val) = arg.strip_prefix("--exclude-line-regex=") {
+            opts.exclude_lines.push(val.to_string());
+        } else if arg == "--exclude-line-regex" {
+            opts.exclude_lines.push(next_value(&args, &mut i, arg)?);
+        } else if let Some(val) = arg.strip_prefix("--allow-regex=") {
+            opts.allow.push(val.to_string());
+        } else if arg == "--allow-regex" {
+            opts.allow.push(next_value(&args, &mut i, arg)?);
+        } else if arg == "--detect-obfuscated" {
+            opts.detect_obfuscated = true;
+        } else if let Some(val) = arg.strip_prefix("--jwt-mode=") {
+            opts.jwt_mode = parse_jwt_mode(val)?;
+        } else if arg == "--jwt-mode" {
+            opts.jwt_mode = parse_jwt_mode(&next_value(&args, &mut i, arg)?)?;
+        } else if let Some(val) = arg.strip_prefix("--email-mode=") {
+            opts.email_mode = Some(parse_email_mode(val)?);
+        } else if arg == "--email-mode" {
+            opts.email_mode = Some(parse_email_mode(&next_value(&args, &mut i, arg)?)?);
+        } else if let Some(val) = arg.strip_prefix("--color=") {
+            opts.color = parse_color(val)?;
+        } else if arg == "--color" {
+            opts.color = ColorChoice::Always;
+        } else if arg == "--no-color" {
+            opts.color = ColorChoice::Never;
+        } else if let Some(val) = arg.strip_prefix("--marker-env=") {
+            opts.marker_env = Some(val.to_string());
+        } else if arg == "--marker-env" {
+            opts.marker_env = Some(next_value(&args, &mut i, arg)?);
+        } else if let Some(val) = arg.strip_prefix("--private-key-structure=") {
+            opts.private_key_structure = parse_private_key_structure(val)?;
+        } else if arg == "--private-key-structure" {
+            opts.private_key_structure =
+                parse_private_key_structure(&next_value(&args, &mut i, arg)?)?;
+        } else if let Some(val) = arg.strip_prefix("--label-map=") {
+            opts.label_map.push(parse_label_map(val)?);
+        } else if arg == "--label-map" {
+            opts.label_map
+                .push(parse_label_map(&next_value(&args, &mut i, arg)?)?);
+        } else if let Some(val) = arg.strip_prefix("--label-map-file=") {
+            opts.label_map.extend(read_label_map_file(val)?);
+        } else if arg == "--label-map-file" {
+            let path = next_value(&args, &mut i, arg)?;
+            opts.label_map.extend(read_label_map_file(&path)?);
+        } else if arg == "--redact-uuids" {
+            opts.redact_uuids = UuidRedaction::All;
+        } else if let Some(val) = arg.strip_prefix("--redact-uuids=") {
+            opts.redact_uuids = parse_redact_uuids(val)?;
+        } else if let Some(val) = arg.strip_prefix("--preset=") {
+            opts.preset = Some(parse_preset(val)?);
+        } else if arg == "--preset" {
+            opts.preset = Some(parse_preset(&next_value(&args, &mut i, arg)?)?);
+        } else if arg == "--no-prefix-echo" {
+            opts.no_prefix_echo = true;
+        } else if arg == "--journal" {
+            opts.journal = true;
+        } else if arg == "--tar" {
+            opts.tar = true;
+        } else if arg == "--count-only" {
+            opts.count_only = true;
+        } else if arg == "--stats" {
+            opts.stats = true;
+        } else if arg == "--diff" {
+            opts.diff = true;
+        } else if arg == "--perf" {
+            opts.perf = true;
+        } else if arg == "--benchmark" {
+            opts.benchmark = true;
+        } else if arg == "--parallel" {
+            opts.parallel =
+                Some(std::thread::available_parallelism().map_or(1, std::num::NonZeroUsize::get));
+        } else if let Some(val) = arg.strip_prefix("--parallel=") {
+            opts.parallel = Some(parse_parallel(val)?);
+        } else if arg == "--progress" {
+            opts.progress = ProgressChoice::Auto;
+        } else if let Some(val) = arg.strip_prefix("--progress=") {
+            opts.progress = parse_progress(val)?;
+        } else if let Some(val) = arg.strip_prefix("--progress-interval=") {
+            opts.progress_interval = Some(parse_progress_interval(val)?);
+        } else if arg == "--progress-interval" {
+            opts.progress_interval =
+                Some(parse_progress_interval(&next_value(&args, &mut i, arg)?)?);
+        } else if let Some(val) = arg.strip_prefix("--min-version=") {
+            check_min_version(val)?;
+        } else if arg == "--min-version" {
+            check_min_version(&next_value(&args, &mut i, arg)?)?;
+        } else if arg == "--whole-word-secrets" {
+            opts.whole_word_secrets = true;
+        } else if arg == "--redact-all-high-entropy-in-json-values" {
+            opts.entropy_json_values = true;
+        } else if arg == "--env-dump" {
+            opts.env_dump = true;
+        } else if arg == "--dump-unmatched-high-entropy" {
+            opts.dump_unmatched_entropy = true;
+        } else if arg == "--fail-on-findings" {
+            opts.fail_on_findings = true;
+        } else if let Some(val) = arg.strip_prefix("--max-input-bytes=") {
+            opts.max_input_bytes = Some(parse_max_input_bytes(val)?);
+        } else if arg == "--max-input-bytes" {
+            opts.max_input_bytes = Some(parse_max_input_bytes(&next_value(&args, &mut i, arg)?)?);
+        } else if let Some(val) = arg.strip_prefix("--sample-rate=") {
+            opts.sample_rate = Some(parse_sample_rate(val)?);
+        } else if arg == "--sample-rate" {
+            opts.sample_rate = Some(parse_sample_rate(&next_value(&args, &mut i, arg)?)?);
+        } else if let Some(val) = arg.strip_prefix("--sample-seed=") {
+            opts.sample_seed = Some(parse_sample_seed(val)?);
+        } else if arg == "--sample-seed" {
+            opts.sample_seed = Some(parse_sample_seed(&next_value(&args, &mut i, arg)?)?);
+        } else if arg == "--pre-commit" {
+            opts.pre_commit = true;
+        } else if arg == "--secrets-from-stdin-header" {
+            opts.secrets_header = true;
+        } else if let Some(val) = arg.strip_prefix("--input=") {
+            set_input(&mut opts, val)?;
+        } else if arg == "--input" {
+            set_input(&mut opts, &next_value(&args, &mut i, arg)?)?;
+        } else if arg == "-" {
+            set_input(&mut opts, "-")?;
+        } else if arg.starts_with('-') {
+            eprintln!("Error: Unknown option: {}", arg);
+            eprintln!("Try 'kahl --help' for more information.");
+            ExitCode::Config.exit();
+        } else if let Some(path) = arg.strip_prefix('@').filter(|p| !p.is_empty()) {
+            set_input(&mut opts, path)?;
+        } else {
+            opts.files.push(arg.clone());
         }
         i += 1;
     }
 
-    // Check for --filter=X or -f X in args
-    let mut cli_filter: Option<String> = None;
-    let mut i = 1;
-    while i < args.len() {
-        if args[i].starts_with("--filter=") {
-            cli_filter = Some(args[i].strip_prefix("--filter=").unwrap().to_string());
-            break;
-        } else if (args[i] == "-f" || args[i] == "--filter") && i + 1 < args.len() {
-            cli_filter = Some(args[i + 1].clone());
-            break;
-        }
-        i += 1;
+    if !opts.files.is_empty() && !opts.pre_commit {
+        return Err(format!(
+            "secrets-filter: unexpected argument '{}' (file arguments require --pre-commit)",
+            opts.files[0]
+        ));
+    }
+    if opts.parallel.is_some()
+        && (opts.journal
+            || opts.tar
+            || opts.pre_commit
+            || opts.perf
+            || opts.sample_rate.is_some()
+            || opts.max_input_bytes.is_some()
+            || !opts.exclude_lines.is_empty()
+            || opts.unsafe_keep_first
+            || opts.dump_unmatched_entropy)
+    {
+        return Err(
+            "secrets-filter: --parallel applies to the plain line stream and cannot be combined \
+             with --journal, --tar, --pre-commit, --perf, --sample-rate, --max-input-bytes, \
+             --exclude-line-regex, --unsafe-keep-first or --dump-unmatched-high-entropy"
+                .to_string(),
+        );
+    }
+    if opts.input.is_some() && (opts.pre_commit || opts.benchmark) {
+        return Err(
+            "secrets-filter: --input cannot be combined with --pre-commit or --benchmark"
+                .to_string(),
+        );
+    }
+    if opts.pre_commit && (opts.journal || opts.count_only || opts.output != OutputFormat::Text) {
+        return Err(
+            "secrets-filter: --pre-commit prints its own report and cannot be combined with \
+             --journal, --count-only or --output=sarif|csv"
+                .to_string(),
+        );
+    }
+
+    if opts.dump_unmatched_entropy && (opts.journal || opts.tar || opts.pre_commit) {
+        return Err(
+            "secrets-filter: --dump-unmatched-high-entropy applies to line input and cannot be \
+             combined with --journal, --tar or --pre-commit"
+                .to_string(),
+        );
+    }
+
+    if opts.max_input_bytes.is_some() && (opts.journal || opts.tar || opts.pre_commit) {
+        return Err(
+            "secrets-filter: --max-input-bytes applies to line input and cannot be combined \
+             with --journal, --tar or --pre-commit"
+                .to_string(),
+        );
+    }
+
+    if opts.journal && (opts.count_only || opts.output != OutputFormat::Text) {
+        return Err(
+            "secrets-filter: --journal writes an export stream and cannot be combined with \
+             --count-only or --output=sarif|csv"
+                .to_string(),
+        );
+    }
+    if opts.fingerprint && opts.hash_salt.as_ref().is_none_or(|salt| salt.is_empty()) {
+        return Err(
+            "secrets-filter: --fingerprint requires --hash-salt or --hash-salt-file".to_string(),
+        );
+    }
+    if opts.unsafe_keep_first_cap.is_some() && !opts.unsafe_keep_first {
+        return Err(
+            "secrets-filter: --unsafe-keep-first-cap requires --unsafe-keep-first".to_string(),
+        );
+    }
+    if opts.only_private_keys && opts.filter.is_some() {
+        return Err(
+            "secrets-filter: --only-private-keys replaces the filters and cannot be combined \
+             with --filter"
+                .to_string(),
+        );
+    }
+    if opts.patterns_url_optional && opts.patterns_url.is_none() {
+        return Err("secrets-filter: --patterns-url-optional requires --patterns-url".to_string());
+    }
+    if opts.sample_seed.is_some() && opts.sample_rate.is_none() {
+        return Err("secrets-filter: --sample-seed requires --sample-rate".to_string());
+    }
+    if opts.sample_rate.is_some() && (opts.journal || opts.tar || opts.pre_commit) {
+        return Err(
+            "secrets-filter: --sample-rate applies to line input and cannot be combined with \
+             --journal, --tar or --pre-commit"
+                .to_string(),
+        );
+    }
+
+    if !opts.exclude_lines.is_empty() && (opts.journal || opts.tar || opts.pre_commit) {
+        return Err(
+            "secrets-filter: --exclude-line-regex applies to line input and cannot be combined \
+             with --journal, --tar or --pre-commit"
+                .to_string(),
+        );
     }
 
+    if opts.tar
+        && (opts.journal || opts.pre_commit || opts.count_only || opts.output != OutputFormat::Text)
+    {
+        return Err(
+            "secrets-filter: --tar writes an archive and cannot be combined with --journal, \
+             --pre-commit, --count-only or --output=sarif|csv"
+                .to_string(),
+        );
+    }
+    if opts.benchmark && (opts.journal || opts.tar || opts.pre_commit || opts.secrets_header) {
+        return Err(
+            "secrets-filter: --benchmark reads no input and cannot be combined with --journal, \
+             --tar, --pre-commit or --secrets-from-stdin-header"
+                .to_string(),
+        );
+    }
+    if opts.diff
+        && (opts.journal
+            || opts.tar
+            || opts.pre_commit
+            || opts.count_only
+            || opts.output != OutputFormat::Text)
+    {
+        return Err(
+            "secrets-filter: --diff compares the lines of the redacted stream and cannot be \
+             combined with --journal, --tar, --pre-commit, --count-only or --output=sarif|csv"
+                .to_string(),
+        );
+    }
+    if opts.count_only && opts.output != OutputFormat::Text {
+        return Err(
+            "secrets-filter: --count-only cannot be combined with --output=sarif|csv".to_string(),
+        );
+    }
+
+    // `env` output in a step is as common as set -x traces
+    if opts.preset == Some(Preset::GithubActions) {
+        opts.env_dump = true;
+    }
+
+    Ok(opts)
+}
+
+/// Resolve filter configuration from the --filter value or environment
+fn parse_filter_config(cli_filter: Option<String>) -> Result<FilterConfig, String> {
     if let Some(filter_str) = cli_filter {
+        // An empty value is a mistake, not a request for defaults or passthrough
+        if filter_str.split(',').all(|part| part.trim().is_empty()) {
+            return Err(
+                "secrets-filter: --filter needs a value; use --filter=none to pass \
+                 input through unchanged, or omit --filter for the defaults"
+                    .to_string(),
+            );
+        }
         // CLI overrides ENV entirely
         let mut values = false;
         let mut patterns = false;
@@ -137,6 +1327,7 @@ fn parse_filter_config() -> Result<FilterConfig, String> {
                     entropy = true;
                     valid_count += 1;
                 }
+                "none" => valid_count += 1,
                 "" => {} // ignore empty parts
                 unknown => {
                     eprintln!("secrets-filter: unknown filter '{}', ignoring", unknown);
@@ -166,7 +1357,7 @@ fn parse_filter_config() -> Result<FilterConfig, String> {
         // Entropy is disabled by default, can be enabled via env var
         let entropy = env::var("SECRETS_FILTER_ENTROPY")
             .map(|v| is_truthy(&v))
-            .unwrap_or(ENTROPY_ENABLED_DEFAULT);
+            .unwrap_or(FilterConfig::default().entropy);
 
         Ok(FilterConfig {
             values,
@@ -176,769 +1367,1064 @@ fn parse_filter_config() -> Result<FilterConfig, String> {
     }
 }
 
-const STATE_NORMAL: u8 = 0;
-const STATE_IN_PRIVATE_KEY: u8 = 1;
-const STATE_IN_PRIVATE_KEY_OVERFLOW: u8 = 2;
-// MAX_PRIVATE_KEY_BUFFER and LONG_THRESHOLD come from patterns_gen
-
-struct Pattern {
-    regex: Regex,
-    label: &'static str,
-}
-
-struct ContextPattern {
-    regex: Regex,
-    label: &'static str,
-    group: usize,
-}
-
-fn build_patterns() -> Vec<Pattern> {
-    PATTERNS
-        .iter()
-        .map(|(regex_str, label)| Pattern {
-            regex: Regex::new(regex_str).unwrap(),
-            label,
-        })
-        .collect()
-}
+/// Fence line opening and closing the --secrets-from-stdin-header block
+const SECRETS_HEADER_FENCE: &str = "--KAHL-SECRETS--";
 
-fn build_context_patterns() -> Vec<ContextPattern> {
-    CONTEXT_PATTERNS
-        .iter()
-        .map(|(regex_str, label, group)| ContextPattern {
-            regex: Regex::new(regex_str).unwrap(),
-            label,
-            group: *group,
-        })
-        .collect()
-}
+/// Consume a leading secrets header block from the input
+///
+/// Returns the NAME=VALUE pairs found between the fences, plus the first line
+/// if the stream did not start with a header (it still has to be filtered).
+/// An unterminated header swallows the whole input (fail closed).
+fn read_secrets_header(input: &mut impl BufRead) -> (Vec<(String, String)>, Option<Vec<u8>>) {
+    let mut secrets = Vec::new();
+    let mut line_buf: Vec<u8> = Vec::new();
 
-fn classify_segment(s: &str) -> String {
-    if s.is_empty() {
-        return String::new();
+    match input.read_until(b'\n', &mut line_buf) {
+        Ok(0) | Err(_) => return (secrets, None),
+        Ok(_) => {}
     }
-    if s.chars().all(|c| c.is_ascii_digit()) {
-        return format!("{}N", s.len());
-    }
-    if s.chars().all(|c| c.is_ascii_alphabetic()) {
-        return format!("{}A", s.len());
-    }
-    format!("{}X", s.len())
-}
-
-fn describe_structure(s: &str) -> String {
-    if s.is_empty() {
-        return String::new();
+    if String::from_utf8_lossy(&line_buf).trim() != SECRETS_HEADER_FENCE {
+        return (secrets, Some(line_buf));
     }
 
-    // Very long tokens
-    if s.len() >= LONG_THRESHOLD {
-        for sep in ["-", "_", "."] {
-            if s.contains(sep) {
-                let parts: Vec<&str> = s.split(sep).collect();
-                let first = parts[0];
-                let is_alpha = first.chars().all(|c| c.is_ascii_alphabetic());
-                let known_prefixes = ["ghp", "gho", "ghs", "ghr", "npm", "sk"];
-                if is_alpha || known_prefixes.contains(&first) {
-                    return format!("{}{}...:{}chars", first, sep, s.len());
-                }
+    loop {
+        line_buf.clear();
+        match input.read_until(b'\n', &mut line_buf) {
+            Ok(0) | Err(_) => {
+                eprintln!("secrets-filter: unterminated secrets header, discarding input");
+                break;
             }
+            Ok(_) => {}
         }
-        return format!("{}chars", s.len());
-    }
-
-    // Check for structured tokens
-    for sep in ["-", ".", "_"] {
-        if s.contains(sep) {
-            let parts: Vec<&str> = s.split(sep).collect();
-            if parts.len() >= 2 {
-                let first = parts[0];
-                let is_alpha = first.chars().all(|c| c.is_ascii_alphabetic());
-                if is_alpha && first.len() <= 12 {
-                    let segments: Vec<String> =
-                        parts[1..].iter().map(|p| classify_segment(p)).collect();
-                    return format!("{}{}{}", first, sep, segments.join(sep));
-                }
-                let segments: Vec<String> = parts.iter().map(|p| classify_segment(p)).collect();
-                return segments.join(sep);
+        let line = String::from_utf8_lossy(&line_buf);
+        let line = line.trim_end_matches(['\r', '\n']);
+        if line.trim() == SECRETS_HEADER_FENCE {
+            break;
+        }
+        if line.trim().is_empty() || line.trim_start().starts_with('#') {
+            continue;
+        }
+        match line.split_once('=') {
+            Some((name, value)) if !name.trim().is_empty() => {
+                secrets.push((name.trim().to_string(), value.to_string()));
             }
+            _ => eprintln!("secrets-filter: ignoring malformed secrets header line"),
         }
     }
 
-    classify_segment(s)
+    (secrets, None)
 }
 
-fn load_secrets() -> HashMap<String, String> {
-    let explicit: HashSet<&str> = EXPLICIT_ENV_VARS.iter().cloned().collect();
+/// What becomes of filtered input
+enum Sink {
+    /// Write the redacted stream
+    Stream,
+    /// Collect findings into a SARIF report
+    Sarif(SarifReport),
+    /// Collect findings into a CSV table
+    Csv(CsvReport),
+    /// Count findings (--count-only)
+    Count,
+}
 
-    let mut secrets = HashMap::new();
+/// Destination for filtered input: the redacted stream, a report or counts
+struct Output<'a> {
+    stdout: io::StdoutLock<'a>,
+    sink: Sink,
+    /// --stats: per-label breakdown (stdout with --count-only, else stderr)
+    stats: bool,
+    /// Findings so far per label (private key blocks count once)
+    labels: BTreeMap<String, usize>,
+    /// --dead-patterns: pattern labels not matched so far
+    unmatched: Option<BTreeSet<String>>,
+    /// Marker regex when markers are highlighted
+    color: Option<regex::Regex>,
+    /// --max-input-bytes: marker ending a truncated stream
+    truncated_marker: String,
+    /// --perf: timing stats
+    perf: Option<Perf>,
+    /// --progress: periodic status on stderr
+    progress: Option<Progress>,
+    /// Findings so far (private key blocks count once)
+    found: usize,
+    /// Lines passed through by --exclude-line-regex
+    excluded: usize,
+    /// --fail-on-findings
+    fail_on_findings: bool,
+    /// --diff: before/after of changed lines on stderr
+    diff: bool,
+}
 
-    for (name, value) in env::vars() {
-        if value.len() < 8 {
+/// `line` with each finding replaced by `<structure>`, for the before side
+/// of --diff (findings are in `line`'s byte offsets)
+fn masked(line: &str, findings: &[Finding]) -> String {
+    let mut spans: Vec<&Finding> = findings.iter().collect();
+    spans.sort_by_key(|f| (f.start, std::cmp::Reverse(f.end)));
+    let mut out = String::with_capacity(line.len());
+    let mut pos = 0;
+    for f in spans {
+        // A span nested in one already masked adds nothing
+        if f.start < pos {
             continue;
         }
-
-        if explicit.contains(name.as_str()) || ENV_SUFFIXES.iter().any(|p| name.ends_with(p)) {
-            secrets.insert(name, value);
-        }
+        out.push_str(&line[pos..f.start]);
+        out.push('<');
+        out.push_str(&f.structure);
+        out.push('>');
+        pos = f.end;
     }
-
-    secrets
+    out.push_str(&line[pos..]);
+    out
 }
 
-fn redact_env_values(text: &str, secrets: &HashMap<String, String>) -> String {
-    if secrets.is_empty() {
-        return text.to_string();
-    }
-
-    // Sort by value length descending
-    let mut sorted: Vec<(&String, &String)> = secrets.iter().collect();
-    sorted.sort_by(|a, b| b.1.len().cmp(&a.1.len()));
-
-    let mut result = text.to_string();
-    for (key, val) in sorted {
-        if !val.is_empty() {
-            let structure = describe_structure(val);
-            let replacement = format!("[REDACTED:{}:{}]", key, structure);
-            result = result.replace(val, &replacement);
+/// ANSI bold red around markers
+const MARKER_COLOR: &str = "\x1b[1;31m";
+const COLOR_RESET: &str = "\x1b[0m";
+
+impl Output<'_> {
+    /// Emit one redacted line (`line` is the original, `line_no` 1-based)
+    fn line(&mut self, line: &str, redacted: &str, findings: &[Finding], line_no: usize) {
+        self.seen(findings);
+        if self.diff && redacted != line {
+            eprintln!(
+                "@@ -{} +{} @@\n-{}\n+{}",
+                line_no,
+                line_no,
+                masked(line, findings).trim_end_matches(['\r', '\n']),
+                redacted.trim_end_matches(['\r', '\n'])
+            );
+        }
+        match &mut self.sink {
+            Sink::Stream => {
+                match &self.color {
+                    Some(marker) => {
+                        let colored = marker.replace_all(redacted, |c: &regex::Captures| {
+                            format!("{}{}{}", MARKER_COLOR, &c[0], COLOR_RESET)
+                        });
+                        let _ = write!(self.stdout, "{}", colored);
+                    }
+                    None => {
+                        let _ = write!(self.stdout, "{}", redacted);
+                    }
+                }
+                let _ = self.stdout.flush();
+            }
+            Sink::Sarif(report) => {
+                for f in findings {
+                    report.add(line, line_no, &f.label, &f.structure, f.start, f.end);
+                }
+            }
+            Sink::Csv(report) => {
+                for f in findings {
+                    report.add(line, line_no, &f.label, &f.structure, f.start, f.source);
+                }
+            }
+            Sink::Count => {}
         }
     }
 
-    result
-}
-
-/// Precompiled special patterns for hot path
-struct SpecialPatterns {
-    git_credential: Regex,
-    docker_auth: Regex,
-}
-
-fn build_special_patterns() -> SpecialPatterns {
-    SpecialPatterns {
-        git_credential: Regex::new(GIT_CREDENTIAL_PATTERN.pattern).unwrap(),
-        docker_auth: Regex::new(DOCKER_AUTH_PATTERN.pattern).unwrap(),
+    /// Note a private key block (--dead-patterns, --fail-on-findings)
+    fn private_key_seen(&mut self) {
+        self.found += 1;
+        *self.labels.entry("PRIVATE_KEY".to_string()).or_default() += 1;
+        if let Some(unmatched) = &mut self.unmatched {
+            unmatched.remove("PRIVATE_KEY");
+        }
     }
-}
-
-fn redact_patterns(
-    text: &str,
-    patterns: &[Pattern],
-    context_patterns: &[ContextPattern],
-    special: &SpecialPatterns,
-) -> String {
-    let mut result = text.to_string();
 
-    // Direct patterns
-    for p in patterns {
-        result = p
-            .regex
-            .replace_all(&result, |caps: &regex::Captures| {
-                let matched = caps.get(0).unwrap().as_str();
-                let structure = describe_structure(matched);
-                format!("[REDACTED:{}:{}]", p.label, structure)
-            })
-            .to_string();
-    }
-
-    // Context patterns (simulate lookbehind)
-    for cp in context_patterns {
-        result = cp
-            .regex
-            .replace_all(&result, |caps: &regex::Captures| {
-                let prefix = caps.get(1).map_or("", |m| m.as_str());
-                let secret = caps.get(cp.group).map_or("", |m| m.as_str());
-                let structure = describe_structure(secret);
-                format!("{}[REDACTED:{}:{}]", prefix, cp.label, structure)
-            })
-            .to_string();
-    }
-
-    // Git credential URLs: ://user:password@ -> ://user:[REDACTED]@
-    result = special
-        .git_credential
-        .replace_all(&result, |caps: &regex::Captures| {
-            let prefix = caps.get(1).map_or("", |m| m.as_str());
-            let password = caps
-                .get(GIT_CREDENTIAL_PATTERN.secret_group)
-                .map_or("", |m| m.as_str());
-            let suffix = caps.get(3).map_or("", |m| m.as_str());
-            let structure = describe_structure(password);
-            format!(
-                "{}[REDACTED:{}:{}]{}",
-                prefix, GIT_CREDENTIAL_PATTERN.label, structure, suffix
-            )
-        })
-        .to_string();
-
-    // Docker config auth: "auth": "base64" -> "auth": "[REDACTED]"
-    result = special
-        .docker_auth
-        .replace_all(&result, |caps: &regex::Captures| {
-            let prefix = caps.get(1).map_or("", |m| m.as_str());
-            let auth = caps
-                .get(DOCKER_AUTH_PATTERN.secret_group)
-                .map_or("", |m| m.as_str());
-            let suffix = caps.get(3).map_or("", |m| m.as_str());
-            let structure = describe_structure(auth);
-            format!(
-                "{}[REDACTED:{}:{}]{}",
-                prefix, DOCKER_AUTH_PATTERN.label, structure, suffix
-            )
-        })
-        .to_string();
-
-    result
-}
-
-// ============================================================================
-// Entropy-based detection
-// ============================================================================
-
-/// Entropy detection configuration (can be overridden via env vars)
-#[derive(Debug, Clone)]
-struct EntropyConfig {
-    threshold_hex: f64,
-    threshold_base64: f64,
-    threshold_alphanumeric: f64,
-    min_length: usize,
-    max_length: usize,
-}
-
-impl Default for EntropyConfig {
-    fn default() -> Self {
-        Self {
-            threshold_hex: ENTROPY_THRESHOLD_HEX,
-            threshold_base64: ENTROPY_THRESHOLD_BASE64,
-            threshold_alphanumeric: ENTROPY_THRESHOLD_ALPHANUMERIC,
-            min_length: ENTROPY_MIN_LENGTH,
-            max_length: ENTROPY_MAX_LENGTH,
+    /// Emit the marker for a private key block spanning `start_line..=end_line`
+    ///
+    /// The marker ends with the block's last line terminator (`eol`), so CRLF
+    /// input keeps CRLF and input without a trailing newline is not given one.
+    fn private_key(&mut self, redactor: &Redactor, start_line: usize, end_line: usize, eol: &str) {
+        self.private_key_seen();
+        let lines = end_line + 1 - start_line;
+        if self.diff {
+            eprintln!(
+                "@@ -{},{} +{} @@\n-<private key block, {} lines>\n+{}",
+                start_line,
+                lines,
+                start_line,
+                lines,
+                redactor.private_key_marker(lines)
+            );
+        }
+        match &mut self.sink {
+            Sink::Stream => self.marker(&redactor.private_key_marker(lines), eol),
+            Sink::Sarif(report) => report.add_lines(
+                "PRIVATE_KEY",
+                &redactor.private_key_structure(lines),
+                start_line,
+                end_line,
+            ),
+            Sink::Csv(report) => report.add_block(
+                "PRIVATE_KEY",
+                &redactor.private_key_structure(lines),
+                start_line,
+            ),
+            Sink::Count => {}
         }
     }
-}
-
-/// Get entropy config with environment variable overrides
-fn get_entropy_config() -> EntropyConfig {
-    let mut config = EntropyConfig::default();
 
-    // Global threshold override
-    if let Ok(val) = env::var("SECRETS_FILTER_ENTROPY_THRESHOLD")
-        && let Ok(t) = val.parse::<f64>()
-    {
-        config.threshold_hex = t;
-        config.threshold_base64 = t;
-        config.threshold_alphanumeric = t;
+    /// Write a standalone marker line to the stream
+    fn marker(&mut self, marker: &str, eol: &str) {
+        let (on, off) = if self.color.is_some() {
+            (MARKER_COLOR, COLOR_RESET)
+        } else {
+            ("", "")
+        };
+        let _ = write!(self.stdout, "{}{}{}{}", on, marker, off, eol);
+        let _ = self.stdout.flush();
     }
 
-    // Per-charset overrides
-    if let Ok(val) = env::var("SECRETS_FILTER_ENTROPY_HEX")
-        && let Ok(t) = val.parse::<f64>()
-    {
-        config.threshold_hex = t;
-    }
-    if let Ok(val) = env::var("SECRETS_FILTER_ENTROPY_BASE64")
-        && let Ok(t) = val.parse::<f64>()
-    {
-        config.threshold_base64 = t;
+    /// End a stream cut off by --max-input-bytes
+    ///
+    /// Truncation is not a finding; reports and counts only get the warning.
+    fn truncated(&mut self, max: u64, eol: &str) {
+        eprintln!(
+            "secrets-filter: input truncated after {} bytes (--max-input-bytes)",
+            max
+        );
+        if self.streams() {
+            let marker = self.truncated_marker.clone();
+            self.marker(&marker, eol);
+        }
     }
 
-    // Length overrides
-    if let Ok(val) = env::var("SECRETS_FILTER_ENTROPY_MIN_LEN")
-        && let Ok(l) = val.parse::<usize>()
-    {
-        config.min_length = l;
-    }
-    if let Ok(val) = env::var("SECRETS_FILTER_ENTROPY_MAX_LEN")
-        && let Ok(l) = val.parse::<usize>()
-    {
-        config.max_length = l;
+    /// Note findings and the pattern labels that matched (--dead-patterns)
+    fn seen(&mut self, findings: &[Finding]) {
+        self.found += findings.len();
+        for f in findings {
+            *self.labels.entry(f.label.clone()).or_default() += 1;
+        }
+        if let Some(unmatched) = &mut self.unmatched {
+            for f in findings.iter().filter(|f| f.source == FilterKind::Patterns) {
+                unmatched.remove(&f.label);
+            }
+        }
     }
 
-    config
-}
-
-/// Calculate Shannon entropy of a string in bits
-/// H = -Σ p(x) log₂ p(x)
-fn shannon_entropy(s: &str) -> f64 {
-    if s.is_empty() {
-        return 0.0;
+    /// Whether the input itself is written out
+    fn streams(&self) -> bool {
+        matches!(self.sink, Sink::Stream)
     }
 
-    let mut counts: HashMap<char, usize> = HashMap::new();
-    for c in s.chars() {
-        *counts.entry(c).or_insert(0) += 1;
+    /// Pass raw bytes through (binary input); nothing to report otherwise
+    fn raw(&mut self, bytes: &[u8]) {
+        if self.streams() {
+            let _ = self.stdout.write_all(bytes);
+            let _ = self.stdout.flush();
+        }
     }
 
-    let length = s.len() as f64;
-    let mut entropy = 0.0;
-    for &count in counts.values() {
-        let p = count as f64 / length;
-        entropy -= p * p.log2();
+    /// Time one call to the redactor (--perf) and count it for --progress
+    fn timed<T>(&mut self, bytes: usize, redact: impl FnOnce() -> T) -> T {
+        let result = match &mut self.perf {
+            Some(perf) => {
+                let start = Instant::now();
+                let result = redact();
+                perf.record(bytes, start.elapsed());
+                result
+            }
+            None => redact(),
+        };
+        self.counted(bytes);
+        result
     }
-    entropy
-}
-
-/// Character set definitions
-const CHARSET_HEX: &str = "0123456789abcdef";
-const CHARSET_BASE64: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/=";
-const CHARSET_ALPHANUMERIC: &str =
-    "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789_-";
 
-/// Classify a string's character set
-/// Returns: "hex", "base64", "alphanumeric", or "mixed"
-fn classify_charset(s: &str) -> &'static str {
-    let lowercase = s.to_lowercase();
-    let chars: HashSet<char> = lowercase.chars().collect();
-    let hex_chars: HashSet<char> = CHARSET_HEX.chars().collect();
-
-    // Check hex first (most restrictive)
-    if chars.iter().all(|c| hex_chars.contains(c)) {
-        return "hex";
+    /// Count one line redacted outside [`Output::timed`] for --progress
+    fn counted(&mut self, bytes: usize) {
+        if let Some(progress) = &mut self.progress {
+            progress.record(bytes);
+        }
     }
 
-    // Check alphanumeric (common for tokens)
-    let alnum_chars: HashSet<char> = CHARSET_ALPHANUMERIC.chars().collect();
-    let original_chars: HashSet<char> = s.chars().collect();
-    if original_chars.iter().all(|c| alnum_chars.contains(c)) {
-        return "alphanumeric";
+    /// `LABEL: N, LABEL: N` for --stats on stderr
+    fn label_summary(&self) -> String {
+        if self.labels.is_empty() {
+            return "nothing".to_string();
+        }
+        self.labels
+            .iter()
+            .map(|(label, n)| format!("{}: {}", label, n))
+            .collect::<Vec<_>>()
+            .join(", ")
     }
 
-    // Check base64
-    let base64_chars: HashSet<char> = CHARSET_BASE64.chars().collect();
-    if original_chars.iter().all(|c| base64_chars.contains(c)) {
-        return "base64";
+    /// Write the report or counts, if any, the dead pattern list and perf
+    /// stats; returns the exit code for the findings
+    fn finish(mut self) -> ExitCode {
+        if let Some(progress) = &self.progress {
+            progress.finish();
+        }
+        if let Some(perf) = &self.perf {
+            eprintln!("{}", perf.summary());
+        }
+        if let Some(unmatched) = &self.unmatched {
+            for label in unmatched {
+                eprintln!("secrets-filter: dead pattern (no matches): {}", label);
+            }
+        }
+        if self.stats && !matches!(self.sink, Sink::Count) {
+            eprintln!("secrets-filter: redacted {}", self.label_summary());
+        }
+        match &self.sink {
+            Sink::Stream => {}
+            Sink::Sarif(report) => {
+                let _ = write!(self.stdout, "{}", report.render(version()));
+            }
+            Sink::Csv(report) => {
+                let _ = write!(self.stdout, "{}", report.render());
+            }
+            Sink::Count => {
+                let _ = writeln!(self.stdout, "{}", self.labels.values().sum::<usize>());
+                if self.stats {
+                    for (label, n) in &self.labels {
+                        let _ = writeln!(self.stdout, "{}: {}", label, n);
+                    }
+                    if self.excluded > 0 {
+                        let _ = writeln!(self.stdout, "skipped lines: {}", self.excluded);
+                    }
+                }
+            }
+        }
+        let _ = self.stdout.flush();
+        if self.fail_on_findings && self.found > 0 {
+            ExitCode::Findings
+        } else {
+            ExitCode::Clean
+        }
     }
-
-    "mixed"
 }
 
-/// Token with position information
-struct Token {
-    text: String,
-    start: usize,
-    end: usize,
+/// Whether unread input is left (for --max-input-bytes)
+fn has_more(input: &mut impl BufRead) -> bool {
+    matches!(input.fill_buf(), Ok(buf) if !buf.is_empty())
 }
 
-/// Extract potential secret tokens from text
-fn extract_tokens(text: &str, min_len: usize, max_len: usize, delim_re: &Regex) -> Vec<Token> {
-    let mut tokens = Vec::new();
-    let mut pos = 0;
-
-    for part in delim_re.split(text) {
-        if !part.is_empty() {
-            // Find the actual position of this part in the original text
-            if let Some(idx) = text[pos..].find(part) {
-                let start = pos + idx;
-                let end = start + part.len();
-                pos = end;
-
-                // Filter by length
-                if part.len() < min_len || part.len() > max_len {
-                    continue;
-                }
-
-                // Skip if all alphabetic (variable names)
-                if part.chars().all(|c| c.is_ascii_alphabetic()) {
-                    continue;
-                }
-
-                // Skip if all numeric (IDs, line numbers)
-                if part.chars().all(|c| c.is_ascii_digit()) {
-                    continue;
-                }
+/// Redact a standalone text (journal MESSAGE value, tar member) line by
+/// line, including private key blocks in it
+fn redact_text(redactor: &mut Redactor, value: &str, out: &mut Output) -> String {
+    let mut redacted = String::with_capacity(value.len());
+    redactor.reset();
+    for line in value.split_inclusive('\n') {
+        match out.timed(line.len(), || redactor.redact_stream_line(line)) {
+            StreamOutput::Line(text, findings) => {
+                out.seen(&findings);
+                redacted.push_str(&text);
+            }
+            StreamOutput::Suppressed => {}
+            StreamOutput::PrivateKey { lines } => {
+                out.private_key_seen();
+                redacted.push_str(&redactor.private_key_marker(lines));
+                redacted.push_str(line_ending(line));
+            }
+        }
+    }
+    if let Some(StreamOutput::PrivateKey { lines }) = redactor.finish_stream() {
+        out.private_key_seen();
+        redacted.push_str(&redactor.private_key_marker(lines));
+    }
+    redacted
+}
 
-                // Skip if contains whitespace
-                if part.chars().any(|c| c.is_whitespace()) {
-                    continue;
+/// Filter journal export input (--journal): MESSAGE values are redacted,
+/// metadata fields and entry boundaries are kept byte for byte
+fn filter_journal(
+    input: &mut impl BufRead,
+    mut first: Option<Vec<u8>>,
+    redactor: &mut Redactor,
+    out: &mut Output,
+) -> io::Result<()> {
+    while let Some(item) = journal::read_item(input, first.take())? {
+        match item {
+            JournalItem::EntryEnd => out.raw(b"\n"),
+            JournalItem::Field {
+                name,
+                value,
+                binary,
+            } => {
+                if name == b"MESSAGE" {
+                    let value = String::from_utf8_lossy(&value);
+                    let redacted = redact_text(redactor, &value, out);
+                    out.raw(&journal::encode_field(&name, redacted.as_bytes(), binary));
+                } else {
+                    out.raw(&journal::encode_field(&name, &value, binary));
                 }
-
-                tokens.push(Token {
-                    text: part.to_string(),
-                    start,
-                    end,
-                });
             }
         }
     }
-
-    tokens
+    Ok(())
 }
 
-/// Check if a position in text is preceded by a context keyword (within 50 chars)
-fn has_context_keyword(text: &str, pos: usize, keywords: &[&str]) -> bool {
-    if keywords.is_empty() {
-        return false;
+/// Redact lines held for an unfinished private key block one by one
+fn flush_held_redacted(redactor: &mut Redactor, last_line_no: usize, out: &mut Output) {
+    let held = redactor.take_held_lines();
+    let first_line_no = last_line_no + 1 - held.len();
+    for (i, line) in held.iter().enumerate() {
+        let (redacted, findings) = redactor.redact_line_with_findings(line);
+        out.line(line, &redacted, &findings, first_line_no + i);
     }
+}
 
-    let start = pos.saturating_sub(50);
-    let prefix = text[start..pos].to_lowercase();
-
-    for kw in keywords {
-        if prefix.contains(&kw.to_lowercase()) {
-            return true;
+/// Lines of a stateless run redacted by one --parallel worker at a time
+const PARALLEL_CHUNK: usize = 256;
+
+/// The line stream with --parallel: batches of lines are read ahead, runs of
+/// lines that carry no stream state are redacted on `workers` threads, and
+/// everything is written in input order. A line that may open a private key
+/// block or another multiline state, and every line while one is open, goes
+/// through the redactor's stream state machine serially, so the output is
+/// byte-identical to the serial loop. Binary input is passed through from
+/// its first NUL line on, as there.
+fn filter_parallel(
+    input: &mut impl BufRead,
+    mut first: Option<Vec<u8>>,
+    redactor: &mut Redactor,
+    out: &mut Output,
+    workers: usize,
+    normalize_unicode: bool,
+) -> io::Result<()> {
+    let mut line_no = 0;
+    // Terminator of the last line read (only the final line may lack one)
+    let mut eol = "\n";
+    let mut batch: Vec<String> = Vec::new();
+    loop {
+        batch.clear();
+        let mut binary = None;
+        while batch.len() < workers * PARALLEL_CHUNK {
+            let mut buf = first.take().unwrap_or_default();
+            if buf.is_empty() && input.read_until(b'\n', &mut buf)? == 0 {
+                break;
+            }
+            if buf.contains(&0) {
+                binary = Some(buf);
+                break;
+            }
+            let line = String::from_utf8_lossy(&buf).into_owned();
+            batch.push(match normalize_unicode.then(|| strip_invisible(&line)) {
+                Some(Some(stripped)) => stripped,
+                _ => line,
+            });
+        }
+        if batch.is_empty() && binary.is_none() {
+            break;
         }
-    }
-
-    false
-}
 
-/// Check if token matches an exclusion pattern
-/// Returns: Some(label) if excluded, None otherwise
-fn matches_exclusion(
-    token: &str,
-    text: &str,
-    pos: usize,
-    exclusion_regexes: &[(Regex, &EntropyExclusion)],
-) -> Option<&'static str> {
-    for (regex, excl) in exclusion_regexes {
-        if regex.is_match(token) {
-            // Check context keywords if present
-            if let Some(context_kw) = excl.context_keywords {
-                if has_context_keyword(text, pos, context_kw) {
-                    return Some(excl.label);
+        let mut i = 0;
+        while i < batch.len() {
+            // A run of lines that neither depend on nor leave stream state
+            let run = if redactor.in_progress() {
+                0
+            } else {
+                batch[i..]
+                    .iter()
+                    .take_while(|line| !redactor.carries_stream_state(line))
+                    .count()
+            };
+            if run < 2 {
+                line_no += 1;
+                let line = &batch[i];
+                eol = line_ending(line);
+                match out.timed(line.len(), || redactor.redact_stream_line(line)) {
+                    StreamOutput::Line(redacted, findings) => {
+                        out.line(line, &redacted, &findings, line_no)
+                    }
+                    StreamOutput::Suppressed => {}
+                    StreamOutput::PrivateKey { lines } => {
+                        out.private_key(redactor, line_no + 1 - lines, line_no, eol);
+                    }
                 }
-                // Has context keywords but none found - not excluded
+                i += 1;
                 continue;
             }
-            // No context keywords required - excluded
-            return Some(excl.label);
+            let lines = &batch[i..i + run];
+            let shared: &Redactor = redactor;
+            let redacted: Vec<(String, Vec<Finding>)> = std::thread::scope(|scope| {
+                let handles: Vec<_> = lines
+                    .chunks(run.div_ceil(workers))
+                    .map(|chunk| {
+                        scope.spawn(move || {
+                            chunk
+                                .iter()
+                                .map(|line| shared.redact_line_with_findings(line))
+                                .collect::<Vec<_>>()
+                        })
+                    })
+                    .collect();
+                handles
+                    .into_iter()
+                    .flat_map(|handle| handle.join().expect("redaction worker panicked"))
+                    .collect()
+            });
+            for (line, (text, findings)) in lines.iter().zip(&redacted) {
+                line_no += 1;
+                out.counted(line.len());
+                out.line(line, text, findings, line_no);
+            }
+            eol = line_ending(&lines[run - 1]);
+            i += run;
         }
-    }
 
-    // Check global context keywords
-    if has_context_keyword(text, pos, ENTROPY_CONTEXT_KEYWORDS) {
-        return Some("CONTEXT");
+        if let Some(buf) = binary {
+            flush_held_redacted(redactor, line_no, out);
+            out.raw(&buf);
+            if out.streams() {
+                io::copy(input, &mut out.stdout)?;
+            } else {
+                io::copy(input, &mut io::sink())?;
+            }
+            return Ok(());
+        }
     }
 
-    None
-}
-
-/// Create structure description for entropy redaction
-/// Example: hex:40:3.8
-fn describe_entropy_structure(token: &str, entropy: f64, charset: &str) -> String {
-    let charset_abbrev = match charset {
-        "hex" => "hex",
-        "base64" => "b64",
-        "alphanumeric" => "alnum",
-        _ => "mix",
-    };
-    format!("{}:{}:{:.1}", charset_abbrev, token.len(), entropy)
+    // EOF: an incomplete private key block is redacted entirely (fail closed)
+    if let Some(StreamOutput::PrivateKey { lines }) = redactor.finish_stream() {
+        out.private_key(redactor, line_no + 1 - lines, line_no, eol);
+    }
+    Ok(())
 }
 
-/// Build compiled exclusion regexes from patterns
-fn build_exclusion_regexes() -> Vec<(Regex, &'static EntropyExclusion)> {
-    ENTROPY_EXCLUSIONS
-        .iter()
-        .filter_map(|excl| {
-            let regex = if excl.case_insensitive {
-                Regex::new(&format!("(?i)^{}$", excl.pattern)).ok()
-            } else {
-                Regex::new(&format!("^{}$", excl.pattern)).ok()
-            };
-            regex.map(|r| (r, excl))
+/// Read the NUL-separated file list given to --pre-commit on stdin
+/// (`git diff --cached --name-only -z`); a trailing newline is tolerated
+fn read_file_list(input: &mut impl BufRead, first: Option<Vec<u8>>) -> Vec<String> {
+    let mut list = first.unwrap_or_default();
+    let _ = input.read_to_end(&mut list);
+    list.split(|&b| b == 0)
+        .map(|name| {
+            String::from_utf8_lossy(name)
+                .trim_end_matches(['\r', '\n'])
+                .to_string()
         })
+        .filter(|name| !name.is_empty())
         .collect()
 }
 
-/// Detect and redact high-entropy strings
-fn redact_entropy(
-    text: &str,
-    config: &EntropyConfig,
-    exclusion_regexes: &[(Regex, &EntropyExclusion)],
-    token_delim_re: &Regex,
-) -> String {
-    let tokens = extract_tokens(text, config.min_length, config.max_length, token_delim_re);
-
-    // Collect replacements (process in reverse order to preserve positions)
-    let mut replacements: Vec<(usize, usize, String)> = Vec::new();
-
-    for token in tokens.iter().rev() {
-        // Check exclusions
-        if matches_exclusion(&token.text, text, token.start, exclusion_regexes).is_some() {
+/// Scan files for --pre-commit, printing `file:line: label` per finding
+///
+/// Files containing a null byte are skipped as binary, like binary stdin
+/// passes through unfiltered; unreadable files are reported on stderr and
+/// make the scan fail with [`ExitCode::Io`] after the others are scanned.
+fn scan_files(files: &[String], redactor: &mut Redactor, stdout: &mut impl Write) -> ExitCode {
+    let mut total = 0;
+    let mut unreadable = false;
+    for path in files {
+        let content = match std::fs::read(path) {
+            Ok(c) => c,
+            Err(e) => {
+                eprintln!("secrets-filter: cannot read '{}': {}", path, e);
+                unreadable = true;
+                continue;
+            }
+        };
+        if content.contains(&0) {
             continue;
         }
-
-        // Classify character set and get threshold
-        let charset = classify_charset(&token.text);
-        let threshold = match charset {
-            "hex" => config.threshold_hex,
-            "base64" => config.threshold_base64,
-            "alphanumeric" => config.threshold_alphanumeric,
-            _ => config.threshold_alphanumeric, // mixed uses alphanumeric threshold
+        let content = String::from_utf8_lossy(&content);
+        let mut report = |line_no: usize, label: &str| {
+            total += 1;
+            let _ = writeln!(stdout, "{}:{}: {}", path, line_no, label);
         };
 
-        // Calculate entropy
-        let entropy = shannon_entropy(&token.text);
-
-        if entropy >= threshold {
-            let structure = describe_entropy_structure(&token.text, entropy, charset);
-            let replacement = format!("[REDACTED:HIGH_ENTROPY:{}]", structure);
-            replacements.push((token.start, token.end, replacement));
+        redactor.reset();
+        let mut line_no = 0;
+        for line in content.split_inclusive('\n') {
+            line_no += 1;
+            match redactor.redact_stream_line(line) {
+                StreamOutput::Line(_, findings) => {
+                    for f in &findings {
+                        report(line_no, &f.label);
+                    }
+                }
+                StreamOutput::Suppressed => {}
+                StreamOutput::PrivateKey { lines } => report(line_no + 1 - lines, "PRIVATE_KEY"),
+            }
+        }
+        if let Some(StreamOutput::PrivateKey { lines }) = redactor.finish_stream() {
+            report(line_no + 1 - lines, "PRIVATE_KEY");
         }
     }
-
-    // Apply replacements in reverse order
-    let mut result = text.to_string();
-    for (start, end, replacement) in replacements {
-        result = format!("{}{}{}", &result[..start], replacement, &result[end..]);
-    }
-
-    result
-}
-
-#[allow(clippy::too_many_arguments)]
-fn redact_line(
-    line: &str,
-    secrets: &HashMap<String, String>,
-    patterns: &[Pattern],
-    context_patterns: &[ContextPattern],
-    special_patterns: &SpecialPatterns,
-    config: &FilterConfig,
-    entropy_config: Option<&EntropyConfig>,
-    exclusion_regexes: &[(Regex, &EntropyExclusion)],
-    token_delim_re: Option<&Regex>,
-) -> String {
-    let mut result = line.to_string();
-    if config.values {
-        result = redact_env_values(&result, secrets);
-    }
-    if config.patterns {
-        result = redact_patterns(&result, patterns, context_patterns, special_patterns);
-    }
-    if config.entropy
-        && let Some(ec) = entropy_config
-        && let Some(delim) = token_delim_re
-    {
-        result = redact_entropy(&result, ec, exclusion_regexes, delim);
-    }
-    result
-}
-
-#[allow(clippy::too_many_arguments)]
-fn flush_buffer_redacted(
-    buffer: &[String],
-    secrets: &HashMap<String, String>,
-    patterns: &[Pattern],
-    context_patterns: &[ContextPattern],
-    special_patterns: &SpecialPatterns,
-    config: &FilterConfig,
-    entropy_config: Option<&EntropyConfig>,
-    exclusion_regexes: &[(Regex, &EntropyExclusion)],
-    token_delim_re: Option<&Regex>,
-) {
-    let stdout = io::stdout();
-    let mut handle = stdout.lock();
-    for line in buffer {
-        let _ = write!(
-            handle,
-            "{}",
-            redact_line(
-                line,
-                secrets,
-                patterns,
-                context_patterns,
-                special_patterns,
-                config,
-                entropy_config,
-                exclusion_regexes,
-                token_delim_re
-            )
-        );
+    let _ = stdout.flush();
+    if unreadable {
+        ExitCode::Io
+    } else if total > 0 {
+        ExitCode::Findings
+    } else {
+        ExitCode::Clean
     }
-    let _ = handle.flush();
 }
 
 fn main() {
-    // Parse filter configuration
-    let config = match parse_filter_config() {
-        Ok(c) => c,
+    // Parse options and filter configuration
+    let (opts, mut config) = match parse_args()
+        .and_then(|mut opts| parse_filter_config(opts.filter.take()).map(|c| (opts, c)))
+    {
+        Ok(r) => r,
         Err(e) => {
             eprintln!("{}", e);
-            std::process::exit(1);
+            ExitCode::Config.exit();
         }
     };
+    if let Err(e) = check_user_patterns(&opts) {
+        eprintln!("{}", e);
+        ExitCode::PatternCompile.exit();
+    }
 
-    // Conditionally load secrets (skip if values filter disabled)
-    let secrets = if config.values {
-        load_secrets()
-    } else {
-        HashMap::new()
-    };
-
-    // Conditionally compile patterns (skip if patterns filter disabled)
-    let patterns = if config.patterns {
-        build_patterns()
-    } else {
-        Vec::new()
-    };
+    // The JSON-values mode is an entropy mode, whatever filters were selected
+    if opts.entropy_json_values {
+        config.entropy = true;
+    }
+    if opts.only_private_keys {
+        config = FilterConfig {
+            values: false,
+            patterns: false,
+            entropy: false,
+        };
+    }
+    if opts.dump_unmatched_entropy && !config.entropy {
+        eprintln!(
+            "secrets-filter: --dump-unmatched-high-entropy needs the entropy filter \
+             (--filter=...,entropy or SECRETS_FILTER_ENTROPY=1)"
+        );
+        ExitCode::Config.exit();
+    }
 
-    let context_patterns = if config.patterns {
-        build_context_patterns()
-    } else {
-        Vec::new()
-    };
+    if let Some(rate) = opts.sample_rate.filter(|&r| r < 1.0) {
+        eprintln!(
+            "secrets-filter: WARNING: --sample-rate={} scans only about {:.1}% of lines; the \
+             rest pass through UNREDACTED. Use it to estimate leak rates, never to prevent leaks.",
+            rate,
+            rate * 100.0
+        );
+    }
 
-    // Special patterns (git credential, docker auth) - always build, cheap if unused
-    let special_patterns = build_special_patterns();
+    if opts.unsafe_keep_first {
+        eprintln!(
+            "secrets-filter: WARNING: --unsafe-keep-first leaves the first occurrence of every \
+             secret UNREDACTED. Use it only to trace where a secret first leaked, on output \
+             that stays in a controlled place."
+        );
+    }
 
-    // Private key detection is part of patterns filter
-    let private_key_begin = if config.patterns {
-        Some(Regex::new(PRIVATE_KEY_BEGIN).unwrap())
-    } else {
-        None
-    };
-    let private_key_end = if config.patterns {
-        Some(Regex::new(PRIVATE_KEY_END).unwrap())
-    } else {
-        None
+    // The stream: stdin, or the --input file (read-only; output is stdout)
+    let mut stdin_handle: Box<dyn BufRead> = match opts.input.as_deref() {
+        None | Some("-") => Box::new(io::stdin().lock()),
+        Some(path) => match std::fs::File::open(path) {
+            Ok(file) => Box::new(io::BufReader::new(file)),
+            Err(e) => {
+                eprintln!("secrets-filter: cannot read --input '{}': {}", path, e);
+                ExitCode::Io.exit();
+            }
+        },
     };
 
-    // Entropy configuration (only if entropy filter enabled)
-    let entropy_config = if config.entropy {
-        Some(get_entropy_config())
+    // Header secrets must be known before the first line is redacted
+    let (header_secrets, mut pending_line) = if opts.secrets_header {
+        read_secrets_header(&mut stdin_handle)
     } else {
-        None
+        (Vec::new(), None)
     };
 
-    // Build exclusion regexes for entropy detection
-    let exclusion_regexes = if config.entropy {
-        build_exclusion_regexes()
-    } else {
-        Vec::new()
+    // Conditionally load secrets (skip if values filter disabled)
+    let mut builder = RedactorBuilder::new()
+        .filters(config)
+        .structure_format(opts.structure_format)
+        .preserve_length(opts.preserve_length)
+        .merge_adjacent(opts.merge_adjacent)
+        .keep_first_occurrence(opts.unsafe_keep_first)
+        .findings_only(opts.count_only || opts.pre_commit)
+        .form_aware(opts.form_aware)
+        .detect_obfuscated(opts.detect_obfuscated)
+        .jwt_mode(opts.jwt_mode)
+        .echo_prefix(!opts.no_prefix_echo)
+        .private_key_structure(opts.private_key_structure)
+        .whole_word_secrets(opts.whole_word_secrets)
+        .entropy_json_values_only(opts.entropy_json_values)
+        .env_dump(opts.env_dump)
+        .normalize_unicode(opts.normalize_unicode)
+        .private_keys_only(opts.only_private_keys)
+        .keep_workflow_commands(opts.preset == Some(Preset::GithubActions));
+    if !opts.entropy_keys.is_empty() {
+        let keys: Vec<&str> = opts.entropy_keys.iter().map(String::as_str).collect();
+        builder = builder.entropy_keys(&keys);
+    }
+    if let Some(cap) = opts.unsafe_keep_first_cap {
+        builder = builder.first_occurrence_cap(cap);
+    }
+    if let Some(mode) = opts.email_mode {
+        builder = builder.redact_emails(mode);
+    }
+    if let Some((start, end)) = &opts.redact_between {
+        builder = builder.redact_between(start, end);
+    }
+    if config.values {
+        builder = builder.secrets_from_env();
+        for (name, value) in &header_secrets {
+            builder = builder.add_secret(name, value);
+        }
+    }
+    if config.entropy {
+        let mut entropy_config = EntropyConfig::from_env();
+        entropy_config.charsets = opts.entropy_charsets;
+        entropy_config.model = opts.entropy_model;
+        builder = builder.entropy_config(entropy_config);
+        for pattern in &opts.entropy_allow {
+            builder = match builder.add_entropy_allow(pattern) {
+                Ok(b) => b,
+                Err(e) => {
+                    eprintln!("secrets-filter: {}", e);
+                    ExitCode::PatternCompile.exit();
+                }
+            };
+        }
+    }
+    if opts.fingerprint
+        && let Some(salt) = &opts.hash_salt
+    {
+        builder = builder.fingerprint(salt.clone());
+    }
+    if opts.hash {
+        let hash = match opts.hash_salt.filter(|salt| !salt.is_empty()) {
+            Some(salt) => SecretHash::Hmac(salt),
+            None => {
+                eprintln!(
+                    "secrets-filter: warning: --hash without --hash-salt uses plain SHA-256; \
+                     short secrets can be recovered by guessing"
+                );
+                SecretHash::Sha256
+            }
+        };
+        builder = builder.hash_secrets(hash);
+    }
+    // Marker prefix from the named env var; brackets and whitespace would
+    // break the marker syntax, so they are dropped
+    let marker_prefix = opts
+        .marker_env
+        .as_deref()
+        .and_then(|var| env::var(var).ok())
+        .map(|v| {
+            v.chars()
+                .filter(|c| !c.is_whitespace() && !matches!(c, '[' | ']'))
+                .collect::<String>()
+        })
+        .filter(|v| !v.is_empty());
+    if let Some(prefix) = &marker_prefix {
+        builder = builder.marker_prefix(prefix);
+    }
+    // Fixed patterns, known to compile
+    builder = match opts.redact_uuids {
+        UuidRedaction::Off => builder,
+        UuidRedaction::All => builder.add_pattern("UUID", UUID_PATTERN).unwrap(),
+        UuidRedaction::Context => builder
+            .add_context_pattern("UUID", UUID_CONTEXT_PATTERN, 2)
+            .unwrap(),
     };
+    if opts.preset == Some(Preset::GithubActions) {
+        builder = builder
+            .add_context_pattern("ACTIONS_MASKED_VALUE", ACTIONS_ADD_MASK_PATTERN, 2)
+            .unwrap()
+            .add_context_pattern("ACTIONS_TRACE_SECRET", ACTIONS_TRACE_PATTERN, 2)
+            .unwrap()
+            .add_context_pattern("ACTIONS_MASK_ADJACENT", ACTIONS_MASK_TAIL_PATTERN, 2)
+            .unwrap()
+            .add_context_pattern("ACTIONS_MASK_ADJACENT", ACTIONS_MASK_HEAD_PATTERN, 2)
+            .unwrap();
+    }
+    if let Some(url) = &opts.patterns_url {
+        for p in load_remote_patterns(url, opts.patterns_url_optional) {
+            let added = if p.context {
+                builder.add_context_pattern(&p.label, &p.regex, 2)
+            } else {
+                builder.add_pattern(&p.label, &p.regex)
+            };
+            builder = added.unwrap_or_else(|e| {
+                eprintln!("secrets-filter: --patterns-url: {}", e);
+                ExitCode::PatternCompile.exit();
+            });
+        }
+    }
+    for pattern in &opts.allow {
+        builder = match builder.add_allow_regex(pattern) {
+            Ok(b) => b,
+            Err(e) => {
+                eprintln!("secrets-filter: {}", e);
+                ExitCode::PatternCompile.exit();
+            }
+        };
+    }
+    if let Some(precision) = opts.entropy_precision {
+        builder = builder.entropy_precision(precision);
+    }
+    if let Some(len) = opts.min_secret_len {
+        builder = builder.min_context_len(len);
+    }
+    if let Some(labels) = opts.only_labels {
+        builder = builder.only_labels(labels);
+    }
+    for (label, name) in &opts.label_map {
+        builder = builder.label_map(label, name);
+    }
+    if opts.benchmark {
+        benchmark::run(builder, config);
+        ExitCode::Clean.exit();
+    }
+    let mut redactor = builder.build();
+    let mut excluded_lines = Vec::new();
+    for pattern in &opts.exclude_lines {
+        match regex::Regex::new(pattern) {
+            Ok(re) => excluded_lines.push(re),
+            Err(e) => {
+                eprintln!(
+                    "secrets-filter: invalid --exclude-line-regex '{}': {}",
+                    pattern, e
+                );
+                ExitCode::PatternCompile.exit();
+            }
+        }
+    }
+    let private_key_begin = regex::Regex::new(PRIVATE_KEY_BEGIN).unwrap();
+    for (label, _) in &opts.label_map {
+        if !redactor.knows_label(label) {
+            eprintln!(
+                "secrets-filter: warning: --label-map: no active filter reports label '{}'",
+                label
+            );
+        }
+    }
 
-    // Token delimiter regex for entropy detection (precompiled)
-    let token_delim_re = if config.entropy {
-        Some(Regex::new(r#"[\s"'`()\[\]{},;:<>=@#]+"#).unwrap())
-    } else {
-        None
+    let stdout = io::stdout();
+    if opts.pre_commit {
+        let files = if opts.files.is_empty() {
+            read_file_list(&mut stdin_handle, pending_line)
+        } else {
+            opts.files
+        };
+        scan_files(&files, &mut redactor, &mut stdout.lock()).exit();
+    }
+    let mut out = Output {
+        stdout: stdout.lock(),
+        sink: if opts.count_only {
+            Sink::Count
+        } else {
+            match opts.output {
+                OutputFormat::Text => Sink::Stream,
+                OutputFormat::Sarif => Sink::Sarif(SarifReport::default()),
+                OutputFormat::Csv => Sink::Csv(CsvReport::default()),
+            }
+        },
+        stats: opts.stats || env::var("SECRETS_FILTER_STATS").is_ok_and(|v| is_truthy(&v)),
+        labels: BTreeMap::new(),
+        color: opts
+            .color
+            .enabled()
+            .then(|| regex::Regex::new(r"\[REDACTED[^\]\n]*\]").unwrap()),
+        truncated_marker: match &marker_prefix {
+            Some(prefix) => format!("[REDACTED:{}:STREAM:truncated]", prefix),
+            None => "[REDACTED:STREAM:truncated]".to_string(),
+        },
+        perf: opts.perf.then(Perf::new),
+        progress: {
+            let tty = io::stderr().is_terminal();
+            let enabled = match opts.progress {
+                ProgressChoice::Never => false,
+                ProgressChoice::Auto => tty,
+                ProgressChoice::Always => true,
+            };
+            let interval = Duration::from_millis(opts.progress_interval.unwrap_or(1000));
+            enabled.then(|| Progress::new(interval, tty))
+        },
+        found: 0,
+        excluded: 0,
+        fail_on_findings: opts.fail_on_findings,
+        diff: opts.diff,
+        unmatched: opts.dead_patterns.then(|| {
+            redactor
+                .pattern_labels()
+                .into_iter()
+                .map(String::from)
+                .collect()
+        }),
     };
+    if opts.journal {
+        // Journal export carries binary fields, so it bypasses line mode and
+        // its null-byte passthrough; markers are never colored there
+        out.color = None;
+        let result = filter_journal(&mut stdin_handle, pending_line, &mut redactor, &mut out);
+        let code = out.finish();
+        if let Err(e) = result {
+            eprintln!("secrets-filter: malformed journal export input: {}", e);
+            ExitCode::Io.exit();
+        }
+        code.exit();
+    }
+    if opts.tar {
+        // Same for archives; a line read for a secrets header is part of it
+        out.color = None;
+        let input = io::Cursor::new(pending_line.unwrap_or_default()).chain(&mut stdin_handle);
+        let result = archive::filter_tar(input, io::stdout(), |text| {
+            redact_text(&mut redactor, text, &mut out)
+        });
+        let code = out.finish();
+        if let Err(e) = result {
+            eprintln!("secrets-filter: error rewriting tar input: {}", e);
+            ExitCode::Io.exit();
+        }
+        code.exit();
+    }
 
-    let mut state = STATE_NORMAL;
-    let mut buffer: Vec<String> = Vec::new();
+    if let Some(workers) = opts.parallel.filter(|&n| n > 1) {
+        let result = filter_parallel(
+            &mut stdin_handle,
+            pending_line,
+            &mut redactor,
+            &mut out,
+            workers,
+            opts.normalize_unicode,
+        );
+        let code = out.finish();
+        if let Err(e) = result {
+            eprintln!("secrets-filter: error reading input: {}", e);
+            ExitCode::Io.exit();
+        }
+        code.exit();
+    }
 
-    let stdin = io::stdin();
-    let stdout = io::stdout();
-    let mut stdout_handle = stdout.lock();
-    let mut stdin_handle = stdin.lock();
     let mut line_buf: Vec<u8> = Vec::new();
+    let mut line_no = 0;
+    // Terminator of the last line read (only the final line may lack one)
+    let mut eol = "\n";
+    let mut read_error = None;
+    // --max-input-bytes: bytes read so far, and whether the limit cut the input
+    let mut consumed: u64 = 0;
+    let mut truncated = false;
 
     loop {
-        line_buf.clear();
-        match stdin_handle.read_until(b'\n', &mut line_buf) {
-            Ok(0) => break, // EOF
-            Ok(_) => {}
-            Err(_) => break,
+        if let Some(first) = pending_line.take() {
+            // First line already read while looking for a secrets header
+            line_buf = first;
+        } else {
+            line_buf.clear();
+            // With a limit, never buffer more than what is left of it
+            let read = match opts.max_input_bytes {
+                Some(max) => (&mut stdin_handle)
+                    .take(max.saturating_sub(consumed))
+                    .read_until(b'\n', &mut line_buf),
+                None => stdin_handle.read_until(b'\n', &mut line_buf),
+            };
+            match read {
+                Ok(0) => {
+                    truncated = opts.max_input_bytes.is_some() && has_more(&mut stdin_handle);
+                    break; // EOF
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    read_error = Some(e);
+                    break;
+                }
+            }
+        }
+        consumed += line_buf.len() as u64;
+
+        // A line cut by the limit is dropped: redacting part of a secret
+        // could leave the rest of it readable
+        if let Some(max) = opts.max_input_bytes
+            && (consumed > max
+                || (consumed == max && !line_buf.ends_with(b"\n") && has_more(&mut stdin_handle)))
+        {
+            truncated = true;
+            break;
         }
 
         // Binary detection: null byte (check raw bytes before UTF-8 conversion)
         if line_buf.contains(&0) {
-            flush_buffer_redacted(
-                &buffer,
-                &secrets,
-                &patterns,
-                &context_patterns,
-                &special_patterns,
-                &config,
-                entropy_config.as_ref(),
-                &exclusion_regexes,
-                token_delim_re.as_ref(),
-            );
-            buffer.clear();
+            flush_held_redacted(&mut redactor, line_no, &mut out);
             // Passthrough this line and rest as raw bytes
-            let _ = stdout_handle.write_all(&line_buf);
-            let _ = stdout_handle.flush();
-            let _ = io::copy(&mut stdin_handle, &mut stdout_handle);
-            return;
+            out.raw(&line_buf);
+            let left = opts
+                .max_input_bytes
+                .map_or(u64::MAX, |max| max.saturating_sub(consumed));
+            let mut rest = (&mut stdin_handle).take(left);
+            let copied = if out.streams() {
+                io::copy(&mut rest, &mut out.stdout)
+            } else {
+                io::copy(&mut rest, &mut io::sink())
+            };
+            if let Some(perf) = &mut out.perf {
+                perf.passthrough(line_buf.len() as u64 + copied.unwrap_or(0));
+            }
+            if let Some(max) = opts.max_input_bytes
+                && has_more(&mut stdin_handle)
+            {
+                out.truncated(max, "\n");
+            }
+            out.finish().exit();
         }
+        line_no += 1;
 
         // Convert to string (lossy for invalid UTF-8 - rare edge case)
         let line = String::from_utf8_lossy(&line_buf).into_owned();
+        eol = line_ending(&line);
+        // --normalize-unicode: reports and --diff refer to the stripped line
+        let line = match opts.normalize_unicode.then(|| strip_invisible(&line)) {
+            Some(Some(stripped)) => stripped,
+            _ => line,
+        };
 
-        match state {
-            STATE_NORMAL => {
-                // Check for private key begin (only if patterns enabled)
-                let is_key_begin = private_key_begin
-                    .as_ref()
-                    .map(|re| re.is_match(&line))
-                    .unwrap_or(false);
-
-                if is_key_begin {
-                    state = STATE_IN_PRIVATE_KEY;
-                    buffer = vec![line];
-                } else {
-                    let _ = write!(
-                        stdout_handle,
-                        "{}",
-                        redact_line(
-                            &line,
-                            &secrets,
-                            &patterns,
-                            &context_patterns,
-                            &special_patterns,
-                            &config,
-                            entropy_config.as_ref(),
-                            &exclusion_regexes,
-                            token_delim_re.as_ref()
-                        )
-                    );
-                    let _ = stdout_handle.flush();
+        // --sample-rate: unsampled lines pass through as they are, unless
+        // they continue a private key block or a JSON value
+        if let Some(rate) = opts.sample_rate
+            && !redactor.in_progress()
+            && !sampled(rate, opts.sample_seed.unwrap_or(0), line_no)
+        {
+            out.line(&line, &line, &[], line_no);
+            continue;
+        }
+
+        // --exclude-line-regex: known-safe lines pass through untouched, but
+        // never a private key BEGIN line or a line continuing earlier ones
+        if !excluded_lines.is_empty()
+            && !redactor.in_progress()
+            && !private_key_begin.is_match(&line)
+            && excluded_lines
+                .iter()
+                .any(|re| re.is_match(line.trim_end_matches(['\r', '\n'])))
+        {
+            out.excluded += 1;
+            out.line(&line, &line, &[], line_no);
+            continue;
+        }
+
+        // Private key blocks are buffered by the redactor and replaced whole
+        match out.timed(line_buf.len(), || redactor.redact_stream_line(&line)) {
+            StreamOutput::Line(redacted, findings) => {
+                out.line(&line, &redacted, &findings, line_no);
+                if opts.dump_unmatched_entropy {
+                    for skip in redactor.entropy_skips(&line) {
+                        eprintln!(
+                            "secrets-filter: unmatched high-entropy token at line {}: {} ({})",
+                            line_no, skip.structure, skip.reason
+                        );
+                    }
                 }
             }
-            STATE_IN_PRIVATE_KEY => {
-                buffer.push(line.clone());
-
-                let is_key_end = private_key_end
-                    .as_ref()
-                    .map(|re| re.is_match(&line))
-                    .unwrap_or(false);
-
-                if is_key_end {
-                    let _ = writeln!(stdout_handle, "[REDACTED:PRIVATE_KEY:multiline]");
-                    let _ = stdout_handle.flush();
-                    buffer.clear();
-                    state = STATE_NORMAL;
-                } else if buffer.len() > MAX_PRIVATE_KEY_BUFFER {
-                    // Buffer overflow - redact entirely (fail closed, don't leak)
-                    let _ = writeln!(stdout_handle, "[REDACTED:PRIVATE_KEY:multiline]");
-                    let _ = stdout_handle.flush();
-                    buffer.clear();
-                    // Transition to overflow state - consume remaining lines silently until END
-                    state = STATE_IN_PRIVATE_KEY_OVERFLOW;
-                }
+            StreamOutput::Suppressed => {}
+            StreamOutput::PrivateKey { lines } => {
+                out.private_key(&redactor, line_no + 1 - lines, line_no, eol);
             }
-            STATE_IN_PRIVATE_KEY_OVERFLOW => {
-                // Consume lines silently until END marker
-                let is_key_end = private_key_end
-                    .as_ref()
-                    .map(|re| re.is_match(&line))
-                    .unwrap_or(false);
-                if is_key_end {
-                    state = STATE_NORMAL;
-                }
-                // No buffering, no output - just wait for END
-            }
-            _ => {}
-        }
-    }
-
-    // EOF: handle remaining state
-    if state == STATE_IN_PRIVATE_KEY {
-        // Incomplete private key block - redact entirely (fail closed, don't leak)
-        let _ = writeln!(stdout_handle, "[REDACTED:PRIVATE_KEY:multiline]");
-    } else if state == STATE_IN_PRIVATE_KEY_OVERFLOW {
-        // Already emitted overflow redaction, nothing to do
-    } else if !buffer.is_empty() {
-        // Flush any remaining buffered content
-        flush_buffer_redacted(
-            &buffer,
-            &secrets,
-            &patterns,
-            &context_patterns,
-            &special_patterns,
-            &config,
-            entropy_config.as_ref(),
-            &exclusion_regexes,
-            token_delim_re.as_ref(),
-        );
+        }
+    }
+
+    // EOF: an incomplete private key block is redacted entirely (fail closed)
+    if let Some(StreamOutput::PrivateKey { lines }) = redactor.finish_stream() {
+        out.private_key(&redactor, line_no + 1 - lines, line_no, eol);
+    }
+    if let Some(max) = opts.max_input_bytes.filter(|_| truncated) {
+        out.truncated(max, eol);
+    }
+    let code = out.finish();
+    if let Some(e) = read_error {
+        eprintln!("secrets-filter: error reading input: {}", e);
+        ExitCode::Io.exit();
     }
+    code.exit();
 }
@@ -0,0 +1,108 @@
+// Throughput and per-line latency stats for --perf
+//
+// Latencies go into a fixed log-linear histogram (8 sub-buckets per power of
+// two nanoseconds), so a long-lived filter uses constant memory and the
+// reported percentiles are within 12.5% of the true value.
+
+use std::time::{Duration, Instant};
+
+const SUB_BUCKETS: usize = 8;
+const BUCKETS: usize = 64 * SUB_BUCKETS;
+
+pub struct Perf {
+    start: Instant,
+    lines: u64,
+    bytes: u64,
+    histogram: Vec<u64>,
+}
+
+/// Histogram bucket for a latency in nanoseconds
+fn bucket(nanos: u64) -> usize {
+    if nanos < SUB_BUCKETS as u64 {
+        return nanos as usize;
+    }
+    let exp = 63 - nanos.leading_zeros() as usize;
+    // The 3 bits below the leading one select the sub-bucket
+    let sub = ((nanos >> (exp - 3)) & (SUB_BUCKETS as u64 - 1)) as usize;
+    (exp - 2) * SUB_BUCKETS + sub
+}
+
+/// Upper bound in nanoseconds of a histogram bucket
+fn bucket_limit(index: usize) -> u64 {
+    if index < SUB_BUCKETS {
+        return index as u64;
+    }
+    let exp = index / SUB_BUCKETS + 2;
+    let sub = (index % SUB_BUCKETS) as u64;
+    ((SUB_BUCKETS as u64 + sub + 1) << (exp - 3)) - 1
+}
+
+/// Human-readable latency (ns, us or ms)
+fn format_latency(nanos: u64) -> String {
+    if nanos < 1_000 {
+        format!("{}ns", nanos)
+    } else if nanos < 1_000_000 {
+        format!("{:.1}us", nanos as f64 / 1e3)
+    } else {
+        format!("{:.1}ms", nanos as f64 / 1e6)
+    }
+}
+
+impl Perf {
+    pub fn new() -> Self {
+        Perf {
+            start: Instant::now(),
+            lines: 0,
+            bytes: 0,
+            histogram: vec![0; BUCKETS],
+        }
+    }
+
+    /// Record one filtered line
+    pub fn record(&mut self, bytes: usize, latency: Duration) {
+        self.lines += 1;
+        self.bytes += bytes as u64;
+        let nanos = u64::try_from(latency.as_nanos()).unwrap_or(u64::MAX);
+        self.histogram[bucket(nanos)] += 1;
+    }
+
+    /// Count bytes passed through without filtering (binary input)
+    pub fn passthrough(&mut self, bytes: u64) {
+        self.bytes += bytes;
+    }
+
+    /// Latency at percentile `p` (0..=100), as a bucket upper bound
+    fn percentile(&self, p: u64) -> u64 {
+        let rank = (self.lines * p).div_ceil(100).max(1);
+        let mut seen = 0;
+        for (i, &n) in self.histogram.iter().enumerate() {
+            seen += n;
+            if seen >= rank {
+                return bucket_limit(i);
+            }
+        }
+        0
+    }
+
+    /// One-line summary for stderr
+    pub fn summary(&self) -> String {
+        let elapsed = self.start.elapsed().as_secs_f64();
+        let throughput = if elapsed > 0.0 {
+            self.bytes as f64 / 1e6 / elapsed
+        } else {
+            0.0
+        };
+        let mut summary = format!(
+            "secrets-filter: perf: {} lines, {} bytes in {:.3}s ({:.2} MB/s)",
+            self.lines, self.bytes, elapsed, throughput
+        );
+        if self.lines > 0 {
+            summary.push_str(&format!(
+                ", line latency p50 {} p99 {}",
+                format_latency(self.percentile(50)),
+                format_latency(self.percentile(99))
+            ));
+        }
+        summary
+    }
+}
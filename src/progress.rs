@@ -0,0 +1,75 @@
+// Progress reports on stderr for --progress
+//
+// On a terminal one status line is rewritten in place; otherwise a full line
+// is written per interval, so captured stderr stays readable. The clock is
+// read only every few lines to keep the per-line cost negligible.
+
+use std::time::{Duration, Instant};
+
+/// Lines between clock reads
+const CHECK_EVERY: u64 = 256;
+
+pub struct Progress {
+    start: Instant,
+    last: Instant,
+    interval: Duration,
+    /// Rewrite one line with `\r` instead of printing a line per report
+    in_place: bool,
+    lines: u64,
+    bytes: u64,
+}
+
+impl Progress {
+    pub fn new(interval: Duration, in_place: bool) -> Self {
+        let now = Instant::now();
+        Progress {
+            start: now,
+            last: now,
+            interval,
+            in_place,
+            lines: 0,
+            bytes: 0,
+        }
+    }
+
+    /// Count one filtered line, reporting if the interval has passed
+    pub fn record(&mut self, bytes: usize) {
+        self.lines += 1;
+        self.bytes += bytes as u64;
+        if !self.lines.is_multiple_of(CHECK_EVERY) {
+            return;
+        }
+        let now = Instant::now();
+        if now.duration_since(self.last) >= self.interval {
+            self.last = now;
+            self.report();
+        }
+    }
+
+    /// Final report at EOF; ends the in-place line
+    pub fn finish(&self) {
+        self.report();
+        if self.in_place {
+            eprintln!();
+        }
+    }
+
+    fn report(&self) {
+        let elapsed = self.start.elapsed().as_secs_f64();
+        let throughput = if elapsed > 0.0 {
+            self.bytes as f64 / 1e6 / elapsed
+        } else {
+            0.0
+        };
+        let status = format!(
+            "secrets-filter: progress: {} lines, {} bytes, {:.2} MB/s",
+            self.lines, self.bytes, throughput
+        );
+        if self.in_place {
+            // Clear what is left of a longer previous status
+            eprint!("\r{}\x1b[K", status);
+        } else {
+            eprintln!("{}", status);
+        }
+    }
+}
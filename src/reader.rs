@@ -0,0 +1,86 @@
+// RedactingReader: a `Read` adapter over `Redactor::redact_stream_line`
+//
+// Input is consumed one line at a time; the redacted line is buffered and
+// handed out across as many `read` calls as the caller needs.
+
+use crate::internal::line_ending;
+use crate::{Redactor, StreamOutput};
+use std::io::{self, BufRead, Read};
+
+/// A reader yielding the redacted content of `inner`
+///
+/// Redaction is line-oriented, so each `read` first pulls a whole line from
+/// `inner` (however long) and redacts it, then serves bytes from that line.
+/// Lines of a private key block are held until the block ends, up to
+/// [`MAX_PRIVATE_KEY_BUFFER`](crate::MAX_PRIVATE_KEY_BUFFER) lines, and come
+/// out as one marker line; a block still open at EOF is replaced as well
+/// (fail closed). A read therefore blocks until `inner` has produced a full
+/// line, or a full block.
+///
+/// Input is decoded as UTF-8; invalid sequences become U+FFFD, so wrap text
+/// streams only.
+pub struct RedactingReader<R> {
+    inner: R,
+    redactor: Redactor,
+    /// Redacted bytes not yet returned, from `pos`
+    out: Vec<u8>,
+    pos: usize,
+    eof: bool,
+}
+
+impl<R: BufRead> RedactingReader<R> {
+    /// Redact `inner` with `redactor`, starting a new stream
+    pub fn new(inner: R, mut redactor: Redactor) -> Self {
+        redactor.reset();
+        Self {
+            inner,
+            redactor,
+            out: Vec::new(),
+            pos: 0,
+            eof: false,
+        }
+    }
+
+    /// The wrapped reader; buffered output not yet read is lost
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Read and redact the next line into `out`, which may stay empty (a
+    /// line held for a private key block)
+    fn fill(&mut self) -> io::Result<()> {
+        self.out.clear();
+        self.pos = 0;
+        let mut line = Vec::new();
+        if self.inner.read_until(b'\n', &mut line)? == 0 {
+            self.eof = true;
+            if let Some(StreamOutput::PrivateKey { lines }) = self.redactor.finish_stream() {
+                self.out
+                    .extend_from_slice(self.redactor.private_key_marker(lines).as_bytes());
+            }
+            return Ok(());
+        }
+        let line = String::from_utf8_lossy(&line);
+        match self.redactor.redact_stream_line(&line) {
+            StreamOutput::Line(text, _) => self.out.extend_from_slice(text.as_bytes()),
+            StreamOutput::Suppressed => {}
+            StreamOutput::PrivateKey { lines } => {
+                self.out
+                    .extend_from_slice(self.redactor.private_key_marker(lines).as_bytes());
+                self.out.extend_from_slice(line_ending(&line).as_bytes());
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<R: BufRead> Read for RedactingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.out.len() && !self.eof {
+            self.fill()?;
+        }
+        let n = (&self.out[self.pos..]).read(buf)?;
+        self.pos += n;
+        Ok(n)
+    }
+}
@@ -0,0 +1,87 @@
+// Centrally managed pattern files for --patterns-url
+//
+// The file is fetched once at startup. A copy is kept in the user's cache
+// directory and used when a later fetch fails, so a flaky network does not
+// silently drop the organization's patterns. Plain HTTP is only accepted for
+// loopback hosts (local mirrors, tests).
+
+use std::path::PathBuf;
+
+/// Give up on a fetch after this long
+#[cfg(feature = "remote-patterns")]
+const FETCH_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Fetch the patterns file at `url`
+#[cfg(feature = "remote-patterns")]
+pub fn fetch(url: &str) -> Result<String, String> {
+    check_scheme(url)?;
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .timeout_global(Some(FETCH_TIMEOUT))
+        .build()
+        .into();
+    agent
+        .get(url)
+        .call()
+        .and_then(|mut response| response.body_mut().read_to_string())
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(not(feature = "remote-patterns"))]
+pub fn fetch(_url: &str) -> Result<String, String> {
+    Err("this build has no remote-patterns support".to_string())
+}
+
+/// Only https, or http to a loopback host
+#[cfg(feature = "remote-patterns")]
+fn check_scheme(url: &str) -> Result<(), String> {
+    if url.starts_with("https://") {
+        return Ok(());
+    }
+    let Some(rest) = url.strip_prefix("http://") else {
+        return Err("expected an https:// URL".to_string());
+    };
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or("");
+    let host = match authority.strip_prefix('[') {
+        Some(v6) => v6.split(']').next().unwrap_or(""),
+        None => authority.split(':').next().unwrap_or(""),
+    };
+    if matches!(host, "localhost" | "127.0.0.1" | "::1") {
+        Ok(())
+    } else {
+        Err("plain http:// is only allowed for localhost; use https://".to_string())
+    }
+}
+
+/// Cached copy of the file last fetched from `url`
+pub fn cached(url: &str) -> Option<String> {
+    std::fs::read_to_string(cache_path(url)?).ok()
+}
+
+/// Keep `content` for later runs; failures only cost the fallback
+pub fn store(url: &str, content: &str) {
+    if let Some(path) = cache_path(url) {
+        if let Some(dir) = path.parent() {
+            let _ = std::fs::create_dir_all(dir);
+        }
+        let _ = std::fs::write(path, content);
+    }
+}
+
+/// `$XDG_CACHE_HOME/kahl/patterns-<hash of url>`, or under `~/.cache`
+fn cache_path(url: &str) -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CACHE_HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+    Some(
+        base.join("kahl")
+            .join(format!("patterns-{:016x}", fnv1a(url))),
+    )
+}
+
+/// FNV-1a, stable across builds (unlike std's hasher)
+fn fnv1a(s: &str) -> u64 {
+    s.bytes().fold(0xcbf2_9ce4_8422_2325, |h, b| {
+        (h ^ b as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
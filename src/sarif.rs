@@ -0,0 +1,130 @@
+// SARIF 2.1.0 report for --output=sarif
+//
+// Minimal document: one run, one rule per label, one result per finding.
+// Messages only carry the redaction marker, never the secret itself.
+
+use std::collections::BTreeSet;
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// Input name used as the artifact location of every result
+const ARTIFACT_URI: &str = "stdin";
+
+/// One result: a label and its region (1-based lines, 1-based code point columns)
+struct SarifResult {
+    label: String,
+    marker: String,
+    start_line: usize,
+    end_line: usize,
+    columns: Option<(usize, usize)>,
+}
+
+/// Findings collected while the input is read, rendered at EOF
+#[derive(Default)]
+pub struct SarifReport {
+    results: Vec<SarifResult>,
+}
+
+impl SarifReport {
+    /// Record a single-line finding; `start`/`end` are byte offsets into `line`
+    pub fn add(
+        &mut self,
+        line: &str,
+        line_no: usize,
+        label: &str,
+        structure: &str,
+        start: usize,
+        end: usize,
+    ) {
+        let start_col = line[..start].chars().count() + 1;
+        let end_col = start_col + line[start..end].chars().count();
+        self.results.push(SarifResult {
+            label: label.to_string(),
+            marker: format!("[REDACTED:{}:{}]", label, structure),
+            start_line: line_no,
+            end_line: line_no,
+            columns: Some((start_col, end_col)),
+        });
+    }
+
+    /// Record a multiline finding covering whole lines (private key blocks)
+    pub fn add_lines(&mut self, label: &str, structure: &str, start_line: usize, end_line: usize) {
+        self.results.push(SarifResult {
+            label: label.to_string(),
+            marker: format!("[REDACTED:{}:{}]", label, structure),
+            start_line,
+            end_line,
+            columns: None,
+        });
+    }
+
+    /// Render the SARIF document
+    pub fn render(&self, tool_version: &str) -> String {
+        let labels: BTreeSet<&str> = self.results.iter().map(|r| r.label.as_str()).collect();
+        let rules: Vec<String> = labels
+            .iter()
+            .map(|l| {
+                format!(
+                    "{{\"id\": {}, \"shortDescription\": {{\"text\": {}}}}}",
+                    json_str(l),
+                    json_str(&format!("Secret detected: {}", l))
+                )
+            })
+            .collect();
+
+        let results: Vec<String> = self
+            .results
+            .iter()
+            .map(|r| {
+                let mut region = format!("\"startLine\": {}", r.start_line);
+                if r.end_line != r.start_line {
+                    region.push_str(&format!(", \"endLine\": {}", r.end_line));
+                }
+                if let Some((start_col, end_col)) = r.columns {
+                    region.push_str(&format!(
+                        ", \"startColumn\": {}, \"endColumn\": {}",
+                        start_col, end_col
+                    ));
+                }
+                format!(
+                    "{{\"ruleId\": {}, \"level\": \"error\", \"message\": {{\"text\": {}}}, \
+                     \"locations\": [{{\"physicalLocation\": {{\"artifactLocation\": {{\"uri\": {}}}, \
+                     \"region\": {{{}}}}}}}]}}",
+                    json_str(&r.label),
+                    json_str(&format!("{} redacted as {}", r.label, r.marker)),
+                    json_str(ARTIFACT_URI),
+                    region
+                )
+            })
+            .collect();
+
+        format!(
+            "{{\"$schema\": {}, \"version\": \"2.1.0\", \"runs\": [{{\"tool\": {{\"driver\": \
+             {{\"name\": \"kahl\", \"version\": {}, \"rules\": [{}]}}}}, \
+             \"columnKind\": \"unicodeCodePoints\", \"results\": [{}]}}]}}\n",
+            json_str(SARIF_SCHEMA),
+            json_str(tool_version),
+            rules.join(", "),
+            results.join(", ")
+        )
+    }
+}
+
+/// Quote and escape a string as a JSON string literal
+fn json_str(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}